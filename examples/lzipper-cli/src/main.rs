@@ -1,13 +1,19 @@
 // SPDX-License-Identifier: GPL-2.0-or-later
 // SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
 
-use lzipper::{Decoder, Encoder, EncoderOptions};
+use lzipper::decoder::CONCATENATED;
+use lzipper::{
+    CompressionLevel, DecodeOptions, Decoder, Encoder, EncoderOptions, Header,
+    decoder_memory_usage, encoder_memory_usage,
+};
+use std::io::IsTerminal;
 use std::path::Path;
 use std::{env, fs::File, io};
 
 struct Args {
     mode: String,
     file_path: String,
+    memusage: bool,
 }
 
 impl Args {
@@ -16,6 +22,7 @@ impl Args {
 
         let mut mode = None;
         let mut file_path = None;
+        let mut memusage = false;
 
         let mut i = 1;
         while i < args.len() {
@@ -26,6 +33,7 @@ impl Args {
                         i += 1;
                     }
                 }
+                "--memusage" => memusage = true,
                 _ => {
                     if file_path.is_none() {
                         file_path = Some(args[i].clone());
@@ -48,10 +56,15 @@ impl Args {
         Args {
             mode: mode.unwrap(),
             file_path: file_path.unwrap(),
+            memusage,
         }
     }
 
     fn validate_file(&self) -> Result<(), &'static str> {
+        if self.file_path == "-" {
+            return Ok(());
+        }
+
         let path = Path::new(&self.file_path);
         if !path.exists() || !path.is_file() {
             return Err("Error: Invalid file or file does not exist.");
@@ -61,35 +74,81 @@ impl Args {
     }
 }
 
-fn compress_file(file_path: &str) -> io::Result<()> {
-    let output_file_path = format!("{}.lz", file_path);
+/// Returns the encoder options, with the level taken from `LZIPPER_LEVEL` if it is set.
+fn encoder_options() -> EncoderOptions {
+    let level = CompressionLevel::from_env("LZIPPER_LEVEL").unwrap_or(CompressionLevel::Default);
+    EncoderOptions::default().level(level)
+}
 
-    let file = File::open(file_path)?;
-    let mut output_file = File::create(&output_file_path)?;
+fn print_memusage(usage: u64) {
+    println!("{} MiB ({} bytes)", usage.div_ceil(1 << 20), usage);
+}
+
+fn compress_memusage() -> io::Result<()> {
+    let usage = encoder_memory_usage(&encoder_options()).map_err(io::Error::other)?;
 
-    let mut encoder = Encoder::new(EncoderOptions::default(), file)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    print_memusage(usage);
+    Ok(())
+}
 
-    encoder
-        .encode(&mut output_file)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+fn decompress_memusage<R: io::Read>(mut input: R) -> io::Result<()> {
+    let mut header = [0; Header::SIZE];
+    input.read_exact(&mut header)?;
+    let usage = Header::from_bytes(&header)
+        .and_then(|header| decoder_memory_usage(header.dict_size))
+        .map_err(io::Error::other)?;
 
-    println!("File written to: {}", &output_file_path);
+    print_memusage(usage);
+    Ok(())
+}
+
+fn compress_file(file_path: &str) -> io::Result<()> {
+    let output_file_path =
+        lzipper::fs::compress_file(Path::new(file_path), None, encoder_options(), true)?;
+
+    println!("File written to: {}", output_file_path.display());
     Ok(())
 }
 
 fn decompress_file(file_path: &str) -> io::Result<()> {
-    let output_file_path = file_path.trim_end_matches(".lz").to_string();
+    let output_file_path =
+        lzipper::fs::decompress_file(Path::new(file_path), None, DecodeOptions::default(), true)?;
+
+    println!("File written to: {}", output_file_path.display());
+    Ok(())
+}
+
+fn test_file<R: io::Read>(input: R) -> io::Result<()> {
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(input, options);
+    let stats = decoder.test().map_err(io::Error::other)?;
+
+    println!("ok ({} members)", stats.members);
+    Ok(())
+}
 
-    let file = File::open(file_path)?;
-    let mut output_file = File::create(&output_file_path)?;
+fn compress_stdio() -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+    if stdout.is_terminal() {
+        eprintln!("Warning: Writing compressed data to a terminal.");
+    }
 
-    let mut decoder = Decoder::new(file);
-    decoder
-        .decode(&mut output_file)
-        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+    // The size of piped input is unknown, so read ahead to size the dictionary of small input.
+    let options = encoder_options().probe_size(1 << 20);
+    let mut encoder = Encoder::new_with_options(io::stdin().lock(), options);
+    encoder.encode(&mut stdout).map_err(io::Error::other)?;
+
+    Ok(())
+}
+
+fn decompress_stdio() -> io::Result<()> {
+    let mut stdout = io::stdout().lock();
+
+    // Like lzip, decompress every member of concatenated input such as `cat a.lz b.lz`.
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(io::stdin().lock(), options);
+    decoder.decode(&mut stdout).map_err(io::Error::other)?;
 
-    println!("File written to: {}", &output_file_path);
     Ok(())
 }
 
@@ -100,10 +159,24 @@ fn main() -> io::Result<()> {
         return Ok(());
     }
 
-    match args.mode.as_str() {
-        "compress" => compress_file(&args.file_path)?,
-        "decompress" => decompress_file(&args.file_path)?,
-        _ => eprintln!("Error: Invalid mode. Use 'compress' or 'decompress'."),
+    if args.memusage {
+        match (args.mode.as_str(), args.file_path.as_str()) {
+            ("compress", _) => compress_memusage()?,
+            ("decompress", "-") => decompress_memusage(io::stdin().lock())?,
+            ("decompress", file_path) => decompress_memusage(File::open(file_path)?)?,
+            _ => eprintln!("Error: --memusage needs mode 'compress' or 'decompress'."),
+        }
+        return Ok(());
+    }
+
+    match (args.mode.as_str(), args.file_path.as_str()) {
+        ("compress", "-") => compress_stdio()?,
+        ("decompress", "-") => decompress_stdio()?,
+        ("test", "-") => test_file(io::stdin().lock())?,
+        ("compress", file_path) => compress_file(file_path)?,
+        ("decompress", file_path) => decompress_file(file_path)?,
+        ("test", file_path) => test_file(File::open(file_path)?)?,
+        _ => eprintln!("Error: Invalid mode. Use 'compress', 'decompress' or 'test'."),
     }
 
     Ok(())
@@ -0,0 +1,38 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+fn compress_memusage_level_9() {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let path = std::env::temp_dir().join(format!("test_memusage_{}.txt", timestamp));
+    std::fs::write(&path, b"the quick brown fox jumps over the lazy dog")
+        .expect("failed to write input file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_lzipper-cli"))
+        .args(["--mode", "compress", "--memusage"])
+        .arg(&path)
+        .env("LZIPPER_LEVEL", "9")
+        .output()
+        .expect("failed to run the cli");
+    std::fs::remove_file(&path).expect("failed to remove input file");
+    assert!(output.status.success());
+
+    // Prints e.g. "674 MiB (705728867 bytes)", and needs more than the 64 MiB dictionary.
+    let stdout = String::from_utf8(output.stdout).expect("invalid output");
+    let mib: u64 = stdout
+        .split_whitespace()
+        .next()
+        .and_then(|mib| mib.parse().ok())
+        .expect("failed to parse output");
+    assert!(
+        mib > 64 && mib < 4096,
+        "implausible memory usage: {}",
+        stdout
+    );
+}
@@ -0,0 +1,39 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use lzipper::Encoder;
+
+#[test]
+fn decompress_stdio_concatenated() {
+    let mut input = Encoder::new(b"the quick brown fox ".as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+    input.extend(
+        Encoder::new(b"jumps over the lazy dog".as_slice())
+            .finish_to_vec()
+            .expect("failed to encode"),
+    );
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_lzipper-cli"))
+        .args(["--mode", "decompress", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("failed to run the cli");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(&input)
+        .expect("failed to write input");
+    let output = child.wait_with_output().expect("failed to run the cli");
+
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        b"the quick brown fox jumps over the lazy dog"
+    );
+}
@@ -0,0 +1,260 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Handles lzip files as a whole, inspecting and rewriting members without decompressing them.
+
+use std::io::{Cursor, Read, Seek, SeekFrom, Write};
+
+use crate::decoder::CONCATENATED;
+use crate::format::{Header, Trailer, member_size};
+use crate::index::index_size;
+use crate::{
+    DecodeOptions, Decoder, Encoder, EncoderOptions, LzipError, decode_dict_size, encode_dict_size,
+};
+
+/// The location and header and trailer fields of a member, found by walking a seekable input.
+pub(crate) struct MemberEntry {
+    /// The offset of the member from where the walk started.
+    pub(crate) offset: u64,
+    /// The size of the whole member, including the header and trailer.
+    pub(crate) member_size: u64,
+    /// The dictionary size declared in the member header.
+    pub(crate) dict_size: u32,
+    /// The uncompressed size stored in the member trailer.
+    pub(crate) uncompressed_size: u64,
+}
+
+/// Rewrites the dictionary size declared in the header of every member, without recompressing.
+///
+/// The members are found from the current position of `stream` to its end, like
+/// [`Decoder::total_uncompressed_size`](crate::Decoder::total_uncompressed_size) does, and the
+/// position is restored afterwards. `new_dict_size` is rounded up to the next size the header
+/// can represent.
+///
+/// Decoding a member needs a dictionary at least as large as the distance of its farthest
+/// match, which is unknown without decompressing. The rewrite is therefore only done if the new
+/// size is at least the member's uncompressed size or its currently declared dictionary size,
+/// which is always safe. Otherwise no member is changed and [`LzipError::InvalidDictSize`] is
+/// returned.
+pub fn rewrite_dict_size<RW: Read + Write + Seek>(
+    stream: &mut RW,
+    new_dict_size: u32,
+) -> Result<(), LzipError> {
+    let dict_byte = encode_dict_size(new_dict_size)?;
+    let dict_size = decode_dict_size(dict_byte)?;
+
+    let start = stream.stream_position()?;
+    let members = walk_members(stream)?;
+
+    let safe = members.iter().all(|member| {
+        dict_size >= member.dict_size || dict_size as u64 >= member.uncompressed_size
+    });
+    if !safe {
+        return Err(LzipError::InvalidDictSize);
+    }
+
+    for member in &members {
+        stream.seek(SeekFrom::Start(start + member.offset + 5))?;
+        stream.write_all(&[dict_byte])?;
+    }
+    stream.seek(SeekFrom::Start(start))?;
+
+    Ok(())
+}
+
+/// Cheaply checks that `data` looks like a complete lzip file, without decompressing it.
+///
+/// The header of the first member is parsed, and the member size in the last trailer is checked
+/// against the length of `data`: it must span all of `data` for a single-member file, or end on
+/// the header of the last member otherwise. No LZMA decoder is allocated, which makes this
+/// suitable as a fast pre-filter over many files.
+///
+/// This is a heuristic, not a verification: it catches a bad header and most truncations, but
+/// not corruption of the compressed data, which only [`Decoder`](crate::Decoder) detects. A
+/// trailing [index](crate::index) is not recognized and makes the check fail.
+pub fn quick_check(data: &[u8]) -> Result<(), LzipError> {
+    let header = data
+        .first_chunk::<{ Header::SIZE }>()
+        .ok_or(LzipError::UnexpectedEndOfStream)?;
+    Header::from_bytes(header)?;
+
+    let trailer = data
+        .last_chunk::<{ Trailer::SIZE }>()
+        .filter(|_| data.len() as u64 >= member_size(0))
+        .ok_or(LzipError::UnexpectedEndOfStream)?;
+    let trailer = Trailer::from_bytes(trailer);
+
+    if trailer.member_size < member_size(0) || trailer.member_size > data.len() as u64 {
+        return Err(LzipError::InvalidMemberSize);
+    }
+
+    // The last member of a multi-member file must start with a header too.
+    let last = &data[data.len() - trailer.member_size as usize..];
+    if last[0..4] != data[0..4] {
+        return Err(LzipError::InvalidMemberSize);
+    }
+
+    Ok(())
+}
+
+/// Splits a multi-member file into one standalone lzip file per member, without recompressing.
+///
+/// Every member is self-contained, so its bytes form a valid single-member file as they are.
+/// The members are found through the member size in each trailer and their headers are
+/// validated. Returns [`LzipError::InvalidMemberSize`] if the members don't line up with
+/// `data`, e.g. because of trailing garbage or a member size overrunning the data, and
+/// [`LzipError::UnexpectedEndOfStream`] if `data` is empty.
+pub fn split_members(data: &[u8]) -> Result<Vec<Vec<u8>>, LzipError> {
+    if data.is_empty() {
+        return Err(LzipError::UnexpectedEndOfStream);
+    }
+
+    let members = walk_members(&mut Cursor::new(data))?;
+    let members = members
+        .iter()
+        .map(|member| {
+            let start = member.offset as usize;
+            data[start..start + member.member_size as usize].to_vec()
+        })
+        .collect();
+
+    Ok(members)
+}
+
+/// Returns the total uncompressed size of the lzip data in `data` without decompressing it.
+///
+/// This is the in-memory counterpart of
+/// [`Decoder::total_uncompressed_size`](crate::Decoder::total_uncompressed_size): the members
+/// are found through the member size in each trailer and their uncompressed sizes summed,
+/// e.g. to preallocate the output. Returns [`LzipError::InvalidMemberSize`] if the members
+/// don't line up with `data`, e.g. because it is truncated or followed by garbage, and
+/// [`LzipError::UnexpectedEndOfStream`] if `data` is empty.
+pub fn uncompressed_size(data: &[u8]) -> Result<u64, LzipError> {
+    if data.is_empty() {
+        return Err(LzipError::UnexpectedEndOfStream);
+    }
+
+    walk_members(&mut Cursor::new(data))?
+        .iter()
+        .try_fold(0u64, |total, member| {
+            total.checked_add(member.uncompressed_size)
+        })
+        .ok_or(LzipError::InvalidDataSize)
+}
+
+/// Recompresses lzip data from `input` to `output` with `options`, in a single streaming pass.
+///
+/// Each member is decompressed straight into an [`Encoder`], which compresses it into a new
+/// member with freshly computed CRC and sizes, so no temporary file is needed and the member
+/// structure is preserved. Every input member is verified against its trailer before its new
+/// trailer is written, and a failing member aborts the transcoding with its error. This is
+/// useful to recompress files at a higher [`CompressionLevel`](crate::CompressionLevel).
+pub fn transcode<R: Read, W: Write>(
+    input: R,
+    output: &mut W,
+    options: EncoderOptions,
+) -> Result<(), LzipError> {
+    let mut decoder = Decoder::new(input);
+
+    loop {
+        Encoder::new_with_options(&mut decoder, options.clone())
+            .encode(output)
+            .map_err(decode_error)?;
+
+        if !decoder.next_member()? {
+            return Ok(());
+        }
+    }
+}
+
+/// Recompresses lzip data from `input` to `output`, regrouping it into members of
+/// `target_member_size` uncompressed bytes each.
+///
+/// All members are decompressed as one stream and compressed again with `options`, split like
+/// [`EncoderOptions::block_size`] does, so a file of many tiny members becomes one of fewer,
+/// larger members, which decode with less per-member overhead, or the other way around. The
+/// decompressed data is preserved exactly, only the member boundaries move; the last member
+/// holds the remainder. A member failing verification aborts the repacking with its error,
+/// possibly after some of its data went into output members already.
+pub fn repack<R: Read, W: Write>(
+    input: R,
+    output: &mut W,
+    target_member_size: u64,
+    options: EncoderOptions,
+) -> Result<(), LzipError> {
+    let decode_options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(input, decode_options);
+
+    Encoder::new_with_options(&mut decoder, options.block_size(target_member_size))
+        .encode(output)
+        .map_err(decode_error)
+}
+
+/// Recovers an error of the decoder read by an encoder from the I/O error wrapping it.
+fn decode_error(err: LzipError) -> LzipError {
+    if let LzipError::IoError(io_err) = &err
+        && let Some(inner) = io_err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<LzipError>())
+    {
+        return inner.clone();
+    }
+
+    err
+}
+
+/// Walks the members from the current position to the end of the input.
+///
+/// The members are returned in input order and the input position is restored afterwards.
+pub(crate) fn walk_members<R: Read + Seek>(input: &mut R) -> Result<Vec<MemberEntry>, LzipError> {
+    let start = input.stream_position()?;
+    let result = walk_members_from(input, start);
+    input.seek(SeekFrom::Start(start))?;
+
+    result
+}
+
+/// Walks the members backwards from the end of the input down to `start`, skipping the
+/// trailing index if there is one.
+fn walk_members_from<R: Read + Seek>(
+    input: &mut R,
+    start: u64,
+) -> Result<Vec<MemberEntry>, LzipError> {
+    let mut members = Vec::new();
+    let index_size = index_size(input, start)?.unwrap_or(0);
+    let mut pos = input.seek(SeekFrom::End(0))? - index_size;
+
+    while pos > start {
+        if pos - start < member_size(0) {
+            return Err(LzipError::InvalidMemberSize);
+        }
+
+        let mut trailer = [0; Trailer::SIZE];
+        input.seek(SeekFrom::Start(pos - Trailer::SIZE as u64))?;
+        input.read_exact(&mut trailer)?;
+        let trailer = Trailer::from_bytes(&trailer);
+
+        if trailer.member_size < member_size(0) || trailer.member_size > pos - start {
+            return Err(LzipError::InvalidMemberSize);
+        }
+        pos -= trailer.member_size;
+
+        let mut header = [0; Header::SIZE];
+        input.seek(SeekFrom::Start(pos))?;
+        input.read_exact(&mut header)?;
+        let header = Header::from_bytes(&header).map_err(|err| match err {
+            LzipError::InvalidMagic => LzipError::InvalidMemberSize,
+            err => err,
+        })?;
+
+        members.push(MemberEntry {
+            offset: pos - start,
+            member_size: trailer.member_size,
+            dict_size: header.dict_size,
+            uncompressed_size: trailer.uncompressed_size,
+        });
+    }
+
+    members.reverse();
+    Ok(members)
+}
@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Handles the decompression of lzip data for async consumers.
+//!
+//! This module is only available with the `tokio` feature.
+
+use std::io::Read;
+use std::panic;
+
+use bytes::Bytes;
+use tokio::sync::mpsc::Sender;
+use tokio::task;
+
+use crate::{DecodeOptions, DecodeStats, Decoder, LzipError};
+
+/// A decoder struct for handing decompressed lzip data to async consumers.
+///
+/// The input is read and decompressed on tokio's blocking thread pool, so the async runtime is
+/// never blocked by I/O on `R` or by LZMA decompression.
+///
+/// # Example
+///
+/// ```no_run
+/// use lzipper::AsyncDecoder;
+///
+/// # async fn example() {
+/// let input = b"compressed data";
+/// let (tx, mut rx) = tokio::sync::mpsc::channel(8);
+///
+/// let decoder = AsyncDecoder::new(input.as_slice());
+/// let handle = tokio::spawn(decoder.decode_to_sender(tx));
+///
+/// while let Some(chunk) = rx.recv().await {
+///     let chunk = chunk.expect("failed to decode");
+///     // process the chunk
+/// }
+/// handle.await.unwrap().expect("failed to decode");
+/// # }
+/// ```
+pub struct AsyncDecoder<R: Read> {
+    /// The decoder doing the actual decompression.
+    decoder: Decoder<R>,
+}
+
+impl<R: Read + Send + 'static> AsyncDecoder<R> {
+    /// Creates a new `AsyncDecoder` instance.
+    ///
+    /// The `input` parameter is a stream of compressed data.
+    pub fn new(input: R) -> Self {
+        Self::new_with_options(input, DecodeOptions::default())
+    }
+
+    /// Creates a new `AsyncDecoder` instance.
+    ///
+    /// The `input` parameter is a stream of compressed data.
+    /// The `options` parameter controls how the data is decompressed.
+    pub fn new_with_options(input: R, options: DecodeOptions) -> Self {
+        AsyncDecoder {
+            decoder: Decoder::new_with_options(input, options),
+        }
+    }
+
+    /// Decompresses the data and sends each decompressed chunk to `tx`.
+    ///
+    /// Sending waits while the channel is full, so a bounded channel applies backpressure to the
+    /// decompression. If decoding fails, including a CRC32 mismatch detected after the last chunk,
+    /// the error is sent as the final item and also returned. If the receiver is dropped,
+    /// decoding stops with [`LzipError::Cancelled`].
+    pub async fn decode_to_sender(
+        self,
+        tx: Sender<Result<Bytes, LzipError>>,
+    ) -> Result<DecodeStats, LzipError> {
+        let mut decoder = self.decoder;
+
+        let handle = task::spawn_blocking(move || {
+            let result = decoder.decode_bytes(|chunk| {
+                tx.blocking_send(Ok(chunk))
+                    .map_err(|_| LzipError::Cancelled)
+            });
+
+            if let Err(err) = &result {
+                let _ = tx.blocking_send(Err(err.clone()));
+            }
+
+            result
+        });
+
+        match handle.await {
+            Ok(result) => result,
+            Err(err) => panic::resume_unwind(err.into_panic()),
+        }
+    }
+}
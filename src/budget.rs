@@ -0,0 +1,94 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! A memory ceiling shared between encoders and decoders.
+
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::LzipError;
+
+/// A ceiling on the memory used by the encoders and decoders sharing it.
+///
+/// [`Encoder`](crate::Encoder)s and [`Decoder`](crate::Decoder)s given the same budget through
+/// [`EncoderOptions::memory_budget`](crate::EncoderOptions::memory_budget) and
+/// [`DecodeOptions::memory_budget`](crate::DecodeOptions::memory_budget) estimate the memory of
+/// each member before creating its LZMA stream, i.e. the dictionary and match finder as
+/// estimated by [`encoder_memory_usage`](crate::encoder_memory_usage) and
+/// [`decoder_memory_usage`](crate::decoder_memory_usage) plus their own buffers, and reserve it
+/// from the budget. If the reservation would exceed the limit, the operation fails with
+/// [`LzipError::MemoryLimitExceeded`] before allocating anything. The memory is given back
+/// once the member is done. Cloning the budget shares it.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::{DecodeOptions, Decoder, Encoder, EncoderOptions, LzipError, MemoryBudget};
+///
+/// let budget = MemoryBudget::new(1 << 20);
+/// let input = b"the quick brown fox jumps over the lazy dog";
+///
+/// // The default level needs far more than 1 MiB to compress.
+/// let options = EncoderOptions::default().memory_budget(budget.clone());
+/// let result = Encoder::new_with_options(input.as_slice(), options).finish_to_vec();
+/// assert_eq!(result, Err(LzipError::MemoryLimitExceeded));
+/// assert_eq!(budget.used(), 0);
+/// ```
+#[derive(Clone, Debug)]
+pub struct MemoryBudget {
+    /// The number of bytes the users of the budget may reserve in total.
+    limit: u64,
+    /// The number of bytes currently reserved.
+    used: Arc<AtomicU64>,
+}
+
+impl MemoryBudget {
+    /// Creates a new `MemoryBudget` allowing `limit` bytes to be reserved at once.
+    pub fn new(limit: u64) -> Self {
+        MemoryBudget {
+            limit,
+            used: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// Returns the number of bytes that may be reserved at once.
+    pub fn limit(&self) -> u64 {
+        self.limit
+    }
+
+    /// Returns the number of bytes currently reserved.
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Acquire)
+    }
+
+    /// Reserves `bytes` from the budget until the returned reservation is dropped.
+    ///
+    /// Returns [`LzipError::MemoryLimitExceeded`] if that would exceed the limit.
+    pub(crate) fn reserve(&self, bytes: u64) -> Result<Reservation, LzipError> {
+        self.used
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |used| {
+                used.checked_add(bytes).filter(|&total| total <= self.limit)
+            })
+            .map_err(|_| LzipError::MemoryLimitExceeded)?;
+
+        Ok(Reservation {
+            budget: self.clone(),
+            bytes,
+        })
+    }
+}
+
+/// Memory reserved from a [`MemoryBudget`], given back when dropped.
+#[derive(Debug)]
+pub(crate) struct Reservation {
+    /// The budget the memory was reserved from.
+    budget: MemoryBudget,
+    /// The number of bytes reserved.
+    bytes: u64,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.budget.used.fetch_sub(self.bytes, Ordering::AcqRel);
+    }
+}
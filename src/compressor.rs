@@ -0,0 +1,103 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Handles the compression of many small inputs into lzip members.
+
+use liblzma::stream::{Action, Filters, Stream};
+
+use crate::encoder::EncoderOptions;
+use crate::format::{Header, Trailer};
+use crate::lzma1::raw_encoder_filters;
+use crate::{LzipError, processed};
+
+/// A compressor turning many small inputs into lzip members with the same options.
+///
+/// LZMA1 needs a fresh stream for every member, but the LZMA options, the filter chain and the
+/// member header are set up once and reused, and members are compressed straight into the
+/// caller's `Vec`. This makes it cheaper than creating an [`Encoder`](crate::Encoder) per
+/// input when compressing a large number of tiny messages.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::{Compressor, EncoderOptions};
+///
+/// let mut compressor = Compressor::new(EncoderOptions::default()).expect("invalid options");
+///
+/// let mut out = Vec::new();
+/// for message in [b"first message".as_slice(), b"second message"] {
+///     out.clear();
+///     compressor
+///         .compress_one(message, &mut out)
+///         .expect("failed to compress");
+/// }
+/// ```
+pub struct Compressor {
+    /// The filter chain every stream is created from.
+    filters: Filters,
+    /// The header shared by every member.
+    header: [u8; Header::SIZE],
+}
+
+impl Compressor {
+    /// Creates a new `Compressor` compressing with the level and dictionary size of `options`.
+    ///
+    /// Returns [`LzipError::InvalidDictSize`] if the dictionary size is not between 4 KiB and
+    /// 512 MiB. The other options, e.g. for splitting the input into blocks, don't apply.
+    pub fn new(options: EncoderOptions) -> Result<Self, LzipError> {
+        let resolved = options.resolve()?;
+        let header = Header::new(resolved.dict_size).to_bytes()?;
+        let filters = raw_encoder_filters(&resolved)?;
+
+        Ok(Compressor { filters, header })
+    }
+
+    /// Compresses `input` into a single member appended to the end of `out`.
+    ///
+    /// On error, `out` is truncated back to its original length.
+    pub fn compress_one(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), LzipError> {
+        let start = out.len();
+        let result = self.compress_member(input, out);
+
+        if result.is_err() {
+            out.truncate(start);
+        }
+        result
+    }
+
+    /// Compresses `input` into a single member appended to the end of `out`.
+    fn compress_member(&mut self, input: &[u8], out: &mut Vec<u8>) -> Result<(), LzipError> {
+        out.extend_from_slice(&self.header);
+
+        let mut stream = Stream::new_raw_encoder(&self.filters)?;
+        let mut pos = 0;
+
+        loop {
+            let eof = pos == input.len();
+
+            out.reserve(4096);
+            let before_len = out.len();
+            let before_in = stream.total_in();
+            stream.process_vec(
+                &input[pos..],
+                out,
+                if eof { Action::Finish } else { Action::Run },
+            )?;
+            pos += processed(before_in, stream.total_in(), input.len() - pos)?;
+            let written = out.len() - before_len;
+
+            if eof && written == 0 {
+                break;
+            }
+        }
+
+        let trailer = Trailer::new(
+            crc32fast::hash(input),
+            input.len() as u64,
+            stream.total_out(),
+        );
+        out.extend_from_slice(&trailer.to_bytes());
+
+        Ok(())
+    }
+}
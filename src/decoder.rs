@@ -3,16 +3,506 @@
 
 //! Handles the decompression of lzip data.
 
-use std::io::{BufRead, BufReader, Read, Write};
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::iter;
+use std::mem;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
 
 use crc32fast::Hasher;
-use liblzma::stream::{Action, Filters, LzmaOptions, Status, Stream};
+use liblzma::stream::{self, Action, Status, Stream};
 
-use crate::LzipError;
-use crate::{LZIP_MAGIC, LZIP_VERSION, LZMA_PRESET_DEFAULT, MAX_DICT_SIZE, MIN_DICT_SIZE};
+use crate::archive::{MemberEntry, walk_members};
+use crate::budget::{MemoryBudget, Reservation};
+use crate::encoder::{CompressionLevel, level_for_dict_size};
+use crate::format::{Header, Trailer, TrailerFormat, member_size};
+use crate::index::{INDEX_MAGIC, IndexEntry, index_body_size, read_index};
+use crate::lzma1::{LzmaParams, decoder_memusage, new_raw_decoder};
+use crate::pool::BufferPool;
+use crate::sink::OutputSink;
+use crate::{LZIP_MAGIC, MAX_DICT_SIZE, MIN_DICT_SIZE};
+use crate::{LzipError, processed};
+
+/// Decode every concatenated member of the input instead of stopping after the first one.
+///
+/// Without this flag only the first member is decoded and any data following it is left unread.
+pub const CONCATENATED: u32 = stream::CONCATENATED;
+
+/// Skip the verification of each member's CRC32.
+///
+/// The uncompressed size and member size stored in the trailer are still verified.
+pub const IGNORE_CHECK: u32 = stream::IGNORE_CHECK;
+
+/// Accepted for compatibility with liblzma, has no effect on lzip decoding.
+///
+/// Every lzip member carries a CRC32, so there is never a missing check to report.
+pub const TELL_NO_CHECK: u32 = stream::TELL_NO_CHECK;
+
+/// Accepted for compatibility with liblzma, has no effect on lzip decoding.
+///
+/// The CRC32 used by lzip is always supported.
+pub const TELL_UNSUPPORTED_CHECK: u32 = stream::TELL_UNSUPPORTED_CHECK;
+
+/// Accepted for compatibility with liblzma, has no effect on lzip decoding.
+///
+/// The check type of an lzip member is always CRC32.
+pub const TELL_ANY_CHECK: u32 = stream::TELL_ANY_CHECK;
+
+/// How long to wait before polling a followed input again, unless set through
+/// [`DecodeOptions::poll_interval`].
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The size of the chunks [`Decoder::verify_parallel`] hands each member to its thread in.
+const VERIFY_CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of chunks queued for each thread of [`Decoder::verify_parallel`].
+const VERIFY_QUEUE_LEN: usize = 2;
+
+/// The number of LZMA data bytes consumed before [`DecodeOptions::max_ratio`] is evaluated.
+pub const MIN_RATIO_INPUT: u64 = 1 << 16;
+
+/// Options controlling how a [`Decoder`] decompresses lzip data.
+///
+/// # Example
+///
+/// ```no_run
+/// use lzipper::decoder::{CONCATENATED, DecodeOptions};
+/// use lzipper::Decoder;
+///
+/// let input = b"compressed data";
+/// let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+/// let mut decoder = Decoder::new_with_options(input.as_slice(), options);
+/// decoder.decode(&mut Vec::new()).expect("failed to decode");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct DecodeOptions {
+    /// The liblzma decoder flags.
+    lzma_flags: u32,
+    /// What to do when a member fails verification.
+    member_error_policy: MemberErrorPolicy,
+    /// The token which cancels decompression once set.
+    cancel_token: Option<Arc<AtomicBool>>,
+    /// The maximum ratio of decompressed to compressed bytes.
+    max_ratio: Option<f64>,
+    /// The pool to borrow working buffers from.
+    buffer_pool: Option<BufferPool>,
+    /// The layout of the member trailers.
+    trailer_format: TrailerFormat,
+    /// The callback notified of tolerated anomalies.
+    warning_handler: Option<WarningHandler>,
+    /// The dictionary size to decode input without the lzip magic with, as raw LZMA1.
+    assume_raw: Option<u32>,
+    /// Whether to wait for more input at the end of the input instead of failing.
+    follow: bool,
+    /// How long to wait before polling a followed input again.
+    poll_interval: Option<Duration>,
+    /// Whether to verify only the sizes in the trailers, without computing the CRC32.
+    structural_only: bool,
+    /// Whether to flush the output after every chunk written by [`Decoder::decode`].
+    flush_each_chunk: bool,
+    /// The nonstandard LZMA parameters the members were compressed with.
+    lzma_params: Option<LzmaParams>,
+    /// How often to retry a read failing with a transient error, and how long to wait first.
+    io_retry: Option<(u32, Duration)>,
+    /// The number of bytes each window of [`Decoder::decode_with_callback`] repeats from the
+    /// previous one.
+    window_overlap: usize,
+    /// The budget the memory of each member is reserved from.
+    memory_budget: Option<MemoryBudget>,
+}
+
+/// What a [`Decoder`] does when a member fails the verification against its trailer.
+///
+/// The policy applies to CRC32, data size and member size mismatches. Errors in the LZMA data
+/// itself or a truncated input always abort decoding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MemberErrorPolicy {
+    /// Abort decoding with the error.
+    #[default]
+    Abort,
+    /// Drop the data of the failed member and continue with the next member.
+    ///
+    /// Each member's data is held back in memory until its trailer has been verified.
+    Skip,
+    /// Keep the data of the failed member and continue with the next member.
+    Emit,
+}
+
+/// A non-fatal anomaly tolerated while decoding, reported through
+/// [`DecodeOptions::on_warning`].
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub enum DecodeWarning {
+    /// The CRC32 of a member didn't match its trailer and [`IGNORE_CHECK`] is set.
+    CrcMismatch {
+        /// The zero-based index of the member.
+        member: u64,
+        /// The CRC32 stored in the trailer.
+        expected: u32,
+        /// The CRC32 of the decompressed data.
+        got: u32,
+    },
+    /// A member failed verification and its data was dropped under [`MemberErrorPolicy::Skip`].
+    MemberSkipped {
+        /// The zero-based index of the member.
+        index: u64,
+        /// The verification error.
+        error: LzipError,
+    },
+    /// A member failed verification and its data was kept under [`MemberErrorPolicy::Emit`].
+    MemberEmitted {
+        /// The zero-based index of the member.
+        index: u64,
+        /// The verification error.
+        error: LzipError,
+    },
+    /// [`Decoder::decode_best_effort`] stopped at corrupt or truncated data.
+    DecodeFailed {
+        /// The compressed bytes consumed from the input when decoding failed.
+        offset: u64,
+        /// The error decoding failed with.
+        error: LzipError,
+    },
+}
+
+/// The callback notified of [`DecodeWarning`]s, shared between clones of the options.
+#[derive(Clone)]
+struct WarningHandler(Arc<Mutex<dyn FnMut(DecodeWarning) + Send>>);
+
+impl fmt::Debug for WarningHandler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("WarningHandler")
+    }
+}
+
+/// The CRC32 and size of a member's uncompressed data, e.g. from a manifest kept alongside a
+/// file, checked by [`Decoder::verify_against`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MemberInfo {
+    /// The CRC32 of the uncompressed data.
+    pub crc32: u32,
+    /// The size of the uncompressed data.
+    pub uncompressed_size: u64,
+}
+
+/// Statistics collected while decoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DecodeStats {
+    /// The number of members decoded.
+    pub members: u64,
+    /// The zero-based indices of the members that failed verification.
+    pub failed_members: Vec<u64>,
+    /// The compression level the data was likely compressed with.
+    ///
+    /// lzip doesn't store the compression level, so this is a heuristic based on the
+    /// dictionary size: it is only set when every member declares the dictionary size this
+    /// crate uses for one of the levels. Data compressed with a custom dictionary size, or by
+    /// other tools with different defaults, may be reported as `None` or as the wrong level.
+    pub inferred_level: Option<CompressionLevel>,
+    /// Where [`Decoder::decode_best_effort`] stopped at corrupt or truncated data, or `None`
+    /// if it decoded everything.
+    pub failure: Option<DecodeFailure>,
+}
+
+/// The point at which [`Decoder::decode_best_effort`] stopped decoding.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DecodeFailure {
+    /// The zero-based index of the member decoding failed in.
+    pub member: u64,
+    /// The compressed bytes consumed from the input when decoding failed, headers and trailers
+    /// included.
+    ///
+    /// The input is read ahead in chunks, so the corruption lies at or before this offset.
+    pub offset: u64,
+    /// The number of decompressed bytes written to the output.
+    pub recovered_size: u64,
+}
+
+impl DecodeOptions {
+    /// Sets the liblzma decoder flags.
+    ///
+    /// lzip members are raw LZMA1 streams framed by this crate, so the flags are interpreted the
+    /// way liblzma's own lzip decoder interprets them. The supported flags are [`CONCATENATED`],
+    /// [`IGNORE_CHECK`], [`TELL_NO_CHECK`], [`TELL_UNSUPPORTED_CHECK`] and [`TELL_ANY_CHECK`].
+    /// Defaults to no flags, which decodes a single member and verifies its CRC32.
+    pub fn lzma_flags(mut self, flags: u32) -> Self {
+        self.lzma_flags = flags;
+        self
+    }
+
+    /// Sets what to do when a member fails verification. Defaults to [`MemberErrorPolicy::Abort`].
+    ///
+    /// Combined with [`CONCATENATED`], this lets the intact members of a damaged multi-member
+    /// file be recovered.
+    pub fn on_member_error(mut self, policy: MemberErrorPolicy) -> Self {
+        self.member_error_policy = policy;
+        self
+    }
+
+    /// Sets a token which cancels decompression once set to `true`.
+    ///
+    /// The token is checked before every chunk of input is decompressed. Once it is set, decoding
+    /// fails with [`LzipError::Cancelled`].
+    pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Sets the maximum ratio of decompressed to compressed bytes, as a guard against
+    /// decompression bombs.
+    ///
+    /// The ratio is evaluated over all members decoded so far, but only once at least
+    /// [`MIN_RATIO_INPUT`] bytes of LZMA data have been consumed, so small inputs with a high
+    /// ratio aren't rejected. Once the ratio is exceeded, decoding fails with
+    /// [`LzipError::RatioExceeded`]. Defaults to no limit.
+    pub fn max_ratio(mut self, ratio: f64) -> Self {
+        self.max_ratio = Some(ratio);
+        self
+    }
+
+    /// Sets a pool to borrow the decoder's working buffers from.
+    ///
+    /// The buffers backing the [`BufRead`] implementation and the data held back under
+    /// [`MemberErrorPolicy::Skip`] are taken from the pool when first needed and given back when
+    /// the decoder is dropped. [`Decoder::decode`] itself decompresses through a buffer on the
+    /// stack and needs none.
+    pub fn buffer_pool(mut self, pool: BufferPool) -> Self {
+        self.buffer_pool = Some(pool);
+        self
+    }
+
+    /// Sets a budget to reserve the memory of decompressing each member from.
+    ///
+    /// Before a member's LZMA stream is created, the memory it needs is estimated like
+    /// [`decoder_memory_usage`] does for the dictionary size in its header, plus the input
+    /// buffer, and reserved from the budget until the member's data is decompressed. If that
+    /// would exceed the budget, decoding fails with [`LzipError::MemoryLimitExceeded`] without
+    /// allocating the dictionary, so a header declaring a huge dictionary can't exhaust the
+    /// memory shared with other decoders.
+    pub fn memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    /// Sets the layout of the member trailers. Defaults to [`TrailerFormat::Version1`].
+    ///
+    /// Members must declare the version matching the format in their header, otherwise
+    /// decoding fails with [`LzipError::UnsupportedVersion`]. Version 0 trailers don't store the
+    /// member size, so it isn't verified for them.
+    pub fn trailer_format(mut self, format: TrailerFormat) -> Self {
+        self.trailer_format = format;
+        self
+    }
+
+    /// Sets the dictionary size to decode input lacking the lzip magic with, as a raw LZMA1
+    /// stream. Defaults to `None`, which fails with [`LzipError::InvalidMagic`] instead.
+    ///
+    /// This is a best-effort recovery mode: the input has no header or trailer, so nothing is
+    /// verified and decoding stops at the end of stream marker. It only applies to the start of
+    /// the input, not to later members. The dictionary size must be between 4 KiB and 512 MiB,
+    /// otherwise decoding fails with [`LzipError::InvalidDictSize`].
+    pub fn assume_raw(mut self, dict_size: Option<u32>) -> Self {
+        self.assume_raw = dict_size;
+        self
+    }
+
+    /// Sets whether to wait for more data when the input ends, like `tail -f`. Defaults to
+    /// `false`.
+    ///
+    /// This is meant for decoding a file that is still being written, such as a live log.
+    /// When the input runs out within a member, the decoder waits for the
+    /// [`DecodeOptions::poll_interval`] and reads again instead of failing with
+    /// [`LzipError::UnexpectedEndOfStream`]. With [`CONCATENATED`], it also waits for the next
+    /// member after each one, so decoding only ends once the
+    /// [`DecodeOptions::cancel_token`] is set, failing with [`LzipError::Cancelled`]. The token
+    /// is checked on every poll.
+    pub fn follow(mut self, follow: bool) -> Self {
+        self.follow = follow;
+        self
+    }
+
+    /// Sets how long to wait before polling a followed input again. Defaults to 100 ms.
+    ///
+    /// Only used with [`DecodeOptions::follow`].
+    pub fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = Some(interval);
+        self
+    }
+
+    /// Sets whether to verify only the sizes stored in each trailer, without computing the
+    /// CRC32 of the decompressed data. Defaults to `false`.
+    ///
+    /// lzip's CRC32 covers every byte of a member, so it can't be checked against a sample of
+    /// the data: it is either computed over all of it or not at all. This mode skips it
+    /// entirely, saving the hashing cost, while the uncompressed size and member size in the
+    /// trailer are still verified, which is cheap. Unlike [`IGNORE_CHECK`], no CRC32 is
+    /// computed and no [`DecodeWarning::CrcMismatch`] is reported.
+    ///
+    /// The sizes catch truncation and framing errors, and damaged LZMA data usually fails to
+    /// decode, but corruption that decodes to the right number of wrong bytes goes unnoticed.
+    /// The CRC32s recorded for [`Decoder::verify_against`] are then meaningless, so the two
+    /// don't combine.
+    pub fn structural_only(mut self, structural_only: bool) -> Self {
+        self.structural_only = structural_only;
+        self
+    }
+
+    /// Sets the literal context (`lc`), literal position (`lp`) and position (`pb`) bits the
+    /// members were compressed with. Defaults to lzip's fixed lc=3, lp=0 and pb=2.
+    ///
+    /// This decodes nonstandard files written with
+    /// [`EncoderOptions::lzma_params`](crate::EncoderOptions::lzma_params). lzip doesn't store
+    /// the parameters, so they have to match the ones used to compress: otherwise decoding
+    /// fails with a stream error or a CRC mismatch. The parameters apply to every member.
+    /// Invalid values, such as `lc + lp` above 4 or `pb` above 4, fail when decoding starts.
+    pub fn lzma_params(mut self, lc: u32, lp: u32, pb: u32) -> Self {
+        self.lzma_params = Some(LzmaParams { lc, lp, pb });
+        self
+    }
+
+    /// Sets how often to retry reading the input after a transient I/O error, and how long to
+    /// wait before the first retry. Defaults to no retries.
+    ///
+    /// This suits readers over unreliable transports which recover from a failed read, such as
+    /// a socket with a read timeout. A read failing with [`io::ErrorKind::Interrupted`],
+    /// [`io::ErrorKind::WouldBlock`], [`io::ErrorKind::TimedOut`],
+    /// [`io::ErrorKind::ConnectionReset`] or [`io::ErrorKind::ConnectionAborted`] is retried
+    /// up to `attempts` times, waiting `backoff` before the first retry and doubling the wait
+    /// before each further one. Any other error, or the last failure once the attempts are
+    /// used up, fails decoding with [`LzipError::IoError`]. No input is lost across retries.
+    pub fn io_retry(mut self, attempts: u32, backoff: Duration) -> Self {
+        self.io_retry = Some((attempts, backoff));
+        self
+    }
+
+    /// Sets how many trailing bytes of each window handed out by
+    /// [`Decoder::decode_with_callback`] are repeated at the start of the next one. Defaults
+    /// to 0.
+    ///
+    /// With an overlap of `k`, every window after the first starts with the last `k` bytes of
+    /// the previous window, followed by newly decompressed data, so a rolling hash or delta
+    /// computation can look back across window boundaries without keeping its own carry
+    /// buffer. The buffer passed to `decode_with_callback` must be larger than `k`, otherwise
+    /// it fails with [`io::ErrorKind::InvalidInput`].
+    pub fn window_overlap(mut self, overlap: usize) -> Self {
+        self.window_overlap = overlap;
+        self
+    }
+
+    /// Sets whether [`Decoder::decode`] flushes the output after every chunk it writes.
+    /// Defaults to `false`.
+    ///
+    /// The output is always flushed once decoding has finished. Flushing every chunk makes
+    /// the data visible as soon as it is decompressed, e.g. to a consumer reading a pipe,
+    /// at the cost of defeating the buffering of writers such as `BufWriter`. Since a chunk
+    /// is written before its member's trailer is verified, the flushed data may still turn
+    /// out to be corrupt.
+    pub fn flush_each_chunk(mut self, flush_each_chunk: bool) -> Self {
+        self.flush_each_chunk = flush_each_chunk;
+        self
+    }
+
+    /// Sets a callback notified of every anomaly tolerated while decoding.
+    ///
+    /// Lenient modes such as [`IGNORE_CHECK`] or a [`MemberErrorPolicy`] other than
+    /// [`MemberErrorPolicy::Abort`] keep decoding through damaged members; the callback makes
+    /// what was tolerated observable without failing the decode. Clones of the options share the
+    /// callback.
+    pub fn on_warning<F>(mut self, handler: F) -> Self
+    where
+        F: FnMut(DecodeWarning) + Send + 'static,
+    {
+        self.warning_handler = Some(WarningHandler(Arc::new(Mutex::new(handler))));
+        self
+    }
+
+    /// Reports a warning to the callback, if there is one.
+    fn warn(&self, warning: DecodeWarning) {
+        if let Some(WarningHandler(handler)) = &self.warning_handler {
+            let mut handler = handler
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            handler(warning);
+        }
+    }
+
+    /// Returns whether decompression has been cancelled.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    }
+
+    /// Sets the given liblzma flags in addition to the ones already set.
+    #[cfg(feature = "fs")]
+    pub(crate) fn add_flags(mut self, flags: u32) -> Self {
+        self.lzma_flags |= flags;
+        self
+    }
+
+    /// Waits for more input to arrive, unless decompression has been cancelled meanwhile.
+    fn poll(&self) -> Result<(), LzipError> {
+        thread::sleep(self.poll_interval.unwrap_or(DEFAULT_POLL_INTERVAL));
+        if self.is_cancelled() {
+            return Err(LzipError::Cancelled);
+        }
+
+        Ok(())
+    }
+
+    /// Fills the buffer of `input`, retrying transient errors as set through
+    /// [`DecodeOptions::io_retry`].
+    fn fill_buf<'a, B: BufRead>(&self, input: &'a mut B) -> io::Result<&'a [u8]> {
+        let (attempts, mut backoff) = self.io_retry.unwrap_or_default();
+
+        let mut retries = 0;
+        loop {
+            match input.fill_buf() {
+                Ok([]) => return Ok(&[]),
+                Ok(_) => break,
+                Err(err) if retries < attempts && is_transient(&err) => {
+                    thread::sleep(backoff);
+                    backoff = backoff.saturating_mul(2);
+                    retries += 1;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        // The data just read is buffered, so it is handed out without reading again.
+        input.fill_buf()
+    }
+
+    /// Returns whether the given flag is set.
+    pub(crate) fn has_flag(&self, flag: u32) -> bool {
+        self.lzma_flags & flag != 0
+    }
+
+    /// Takes a buffer from the pool, or returns a new empty buffer without a pool.
+    fn take_buffer(&self) -> Vec<u8> {
+        self.buffer_pool
+            .as_ref()
+            .map_or_else(Vec::new, BufferPool::take)
+    }
+}
 
 /// A decoder struct for decompressing lzip data.
 ///
+/// Besides [`Decoder::decode`], the decompressed data can be pulled through the [`Read`] and
+/// [`BufRead`] implementations, e.g. to iterate over the lines of a compressed log.
+///
+/// A `Decoder` is [`Send`] and [`Sync`] whenever `R` is, including while a member is being
+/// decompressed, since the LZMA stream it holds is both too. The callback passed to
+/// [`DecodeOptions::on_warning`] is required to be `Send` for the same reason. As with
+/// [`Encoder`](crate::Encoder), moving it into [`std::thread::spawn`] also requires a
+/// `'static` input.
+///
 /// # Example
 ///
 /// ```no_run
@@ -26,15 +516,87 @@ use crate::{LZIP_MAGIC, LZIP_VERSION, LZMA_PRESET_DEFAULT, MAX_DICT_SIZE, MIN_DI
 /// ```
 pub struct Decoder<R: Read> {
     /// The compressed data input stream.
+    ///
+    /// Headers, LZMA data and trailers are all read through this one buffer, so no bytes are
+    /// lost however the underlying reader splits up its data.
     input: BufReader<R>,
+    /// The options used for decompression.
+    options: DecodeOptions,
     /// The dictionary size to use for decompression.
     dict_size: u32,
+    /// The stage the decoder is currently in.
+    stage: Stage,
+    /// The LZMA stream of the member being decompressed.
+    stream: Option<Stream>,
+    /// The memory of `stream` reserved from the memory budget.
+    reservation: Option<Reservation>,
+    /// The CRC32 hasher of the member being decompressed.
+    hasher: Hasher,
     /// The CRC32 of the uncompressed data.
     crc32: u32,
     // The size of the uncompressed data.
     uncompressed_size: u64,
     // The size of the compressed data.
     compressed_size: u64,
+    /// Decompressed data handed out through `BufRead`.
+    buffer: Vec<u8>,
+    /// The position of the first unconsumed byte in `buffer`.
+    pos: usize,
+    /// The number of decompressed bytes in `buffer`.
+    filled: usize,
+    /// Data of the current member held back until its trailer is verified.
+    held: Vec<u8>,
+    /// Verified data ready to be handed out.
+    ready: Vec<u8>,
+    /// The position of the first byte in `ready` not handed out yet.
+    ready_pos: usize,
+    /// The statistics collected so far.
+    stats: DecodeStats,
+    /// The LZMA data bytes consumed across all members.
+    total_in: u64,
+    /// The bytes decompressed across all members.
+    total_out: u64,
+    /// The compressed bytes consumed from the input, headers and trailers included.
+    consumed: u64,
+    /// The offset in the input where the compressed data of the current member starts.
+    data_start: u64,
+    /// The trailer supplied out of band for a headerless payload.
+    external_trailer: Option<Trailer>,
+    /// Whether the input is decoded as raw LZMA1 without any framing.
+    raw: bool,
+    /// The CRC32 and size of every member decoded so far, while recording them.
+    member_infos: Option<Vec<MemberInfo>>,
+    /// The trailer of every member read so far, as stored in the input.
+    trailers: Vec<Trailer>,
+    /// The compressed bytes pushed so far, if the input is fed through
+    /// [`Decoder::push_frame`].
+    pushed: Option<u64>,
+}
+
+/// The stages a decoder goes through for each member.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    /// The member header is next in the input.
+    Header,
+    /// The compressed data of the member is being decompressed.
+    Data,
+    /// The member trailer is next in the input.
+    Trailer,
+    /// All members have been decoded.
+    Done,
+}
+
+/// The state of a [`Decoder`], as returned by [`Decoder::state`] and [`Decoder::step`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecoderState {
+    /// The header of the next member is read by the next step.
+    ReadingHeader,
+    /// The compressed data of the current member is being decompressed.
+    Decompressing,
+    /// The trailer of the current member is read by the next step.
+    ReadingTrailer,
+    /// All members have been decoded.
+    Done,
 }
 
 impl<R: Read> Decoder<R> {
@@ -42,126 +604,1529 @@ impl<R: Read> Decoder<R> {
     ///
     /// The `input` parameter is a stream of compressed data.
     pub fn new(input: R) -> Self {
+        Self::new_with_options(input, DecodeOptions::default())
+    }
+
+    /// Creates a new `Decoder` instance.
+    ///
+    /// The `input` parameter is a stream of compressed data.
+    /// The `options` parameter controls how the data is decompressed.
+    pub fn new_with_options(input: R, options: DecodeOptions) -> Self {
         Decoder {
             input: BufReader::new(input),
+            options,
             dict_size: 0,
+            stage: Stage::Header,
+            stream: None,
+            reservation: None,
+            hasher: Hasher::new(),
             crc32: 0,
             uncompressed_size: 0,
             compressed_size: 0,
+            buffer: Vec::new(),
+            pos: 0,
+            filled: 0,
+            held: Vec::new(),
+            ready: Vec::new(),
+            ready_pos: 0,
+            stats: DecodeStats::default(),
+            total_in: 0,
+            total_out: 0,
+            consumed: 0,
+            data_start: 0,
+            external_trailer: None,
+            raw: false,
+            member_infos: None,
+            trailers: Vec::new(),
+            pushed: None,
+        }
+    }
+
+    /// Creates a new `Decoder` instance for a bare LZMA1 payload whose header and trailer are
+    /// supplied out of band.
+    ///
+    /// The `input` parameter is the payload written by
+    /// [`Encoder::encode_headerless`](crate::Encoder::encode_headerless), which is decompressed
+    /// with `dict_size` and verified against `expected_crc` and `expected_size` instead of a
+    /// trailer. This saves the 26 bytes of framing per message when both ends already agree on
+    /// them.
+    ///
+    /// Returns [`LzipError::InvalidDictSize`] if the size is not between 4 KiB and 512 MiB.
+    pub fn new_headerless(
+        input: R,
+        dict_size: u32,
+        expected_crc: u32,
+        expected_size: u64,
+    ) -> Result<Self, LzipError> {
+        if !(MIN_DICT_SIZE..=MAX_DICT_SIZE).contains(&dict_size) {
+            return Err(LzipError::InvalidDictSize);
         }
+
+        let mut decoder = Self::new(input);
+        decoder.begin_member(dict_size);
+        decoder.external_trailer = Some(Trailer::new(expected_crc, expected_size, 0));
+
+        Ok(decoder)
+    }
+
+    /// Creates a new `Decoder` instance for lzip data starting `skip` bytes into `input`, e.g.
+    /// after the header of an enclosing format.
+    ///
+    /// The skipped bytes are read and discarded through the decoder's own buffer, so no data
+    /// is lost to buffering. Seekable inputs can skip them without reading through
+    /// [`Decoder::with_offset_seekable`]. Offsets reported later, e.g. by
+    /// [`Decoder::compressed_bytes_consumed`], count from the start of the lzip data.
+    ///
+    /// Returns [`LzipError::UnexpectedEndOfStream`] if the input ends within the skipped bytes.
+    pub fn with_offset(input: R, skip: u64) -> Result<Self, LzipError> {
+        let mut decoder = Self::new(input);
+        let skipped = io::copy(&mut (&mut decoder.input).take(skip), &mut io::sink())?;
+        if skipped < skip {
+            return Err(LzipError::UnexpectedEndOfStream);
+        }
+
+        Ok(decoder)
     }
 
     /// Decompresses the data from the input stream and writes it to the output stream.
     ///
-    /// The `output` parameter is a writable stream where the decompressed data will be written.
-    pub fn decode<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
-        self.read_header()?;
-        self.decompress(output)?;
-        self.read_trailer()?;
+    /// The `output` parameter is a writable stream where the decompressed data will be written,
+    /// or any other [`OutputSink`]. Once the last trailer has been verified, the output is
+    /// flushed. Returns the statistics collected while decoding.
+    pub fn decode<S: OutputSink + ?Sized>(
+        &mut self,
+        output: &mut S,
+    ) -> Result<DecodeStats, LzipError> {
+        let flush_each_chunk = self.options.flush_each_chunk;
+        let stats = self.decode_chunks(|chunk| {
+            output.write_chunk(chunk)?;
+            if flush_each_chunk {
+                output.flush_output()?;
+            }
+
+            Ok(())
+        })?;
+        output.flush_output()?;
+
+        Ok(stats)
+    }
+
+    /// Decompresses the data from the input stream and verifies it, discarding the output.
+    ///
+    /// The decompressed data is verified against each member's trailer like [`Decoder::decode`]
+    /// does, but never buffered beyond a single chunk, so testing the integrity of a file takes
+    /// constant memory however large the decompressed data is.
+    pub fn test(&mut self) -> Result<DecodeStats, LzipError> {
+        self.decode_chunks(|_| Ok(()))
+    }
+
+    /// Decompresses the data from the input stream and writes as much of it as possible to the
+    /// output stream, for recovering data from a damaged file.
+    ///
+    /// Decoding runs like [`Decoder::decode`] until it fails, be it on a damaged LZMA stream,
+    /// truncated input or a member failing verification. Instead of returning that error, the
+    /// data decompressed up to that point is kept, including data of the failing member held
+    /// back by [`MemberErrorPolicy::Skip`], a [`DecodeWarning::DecodeFailed`] is reported and
+    /// the statistics are returned with [`DecodeStats::failure`] telling how much was recovered
+    /// and where decoding stopped. The decoder is finished afterwards.
+    ///
+    /// Only damaged or truncated data is recovered from: [`LzipError::Corrupt`], trailer
+    /// mismatches, [`LzipError::UnexpectedEndOfStream`], [`LzipError::StreamError`], input
+    /// ending early, and a damaged header after the first member. Anything else is returned as
+    /// is, such as [`LzipError::Cancelled`], [`LzipError::MemoryLimitExceeded`], other I/O
+    /// errors, errors writing to the output, and an input not starting with an lzip member.
+    ///
+    /// Data written before the point of failure has not been verified against the trailer of
+    /// its member, so it may be corrupt as well.
+    pub fn decode_best_effort<S: OutputSink + ?Sized>(
+        &mut self,
+        output: &mut S,
+    ) -> Result<DecodeStats, LzipError> {
+        let mut recovered_size = 0;
+        let mut output_failed = false;
+        let result = self.decode_chunks(|chunk| {
+            output
+                .write_chunk(chunk)
+                .inspect_err(|_| output_failed = true)?;
+            recovered_size += chunk.len() as u64;
+
+            Ok(())
+        });
+
+        let error = match result {
+            Ok(stats) => {
+                output.flush_output()?;
+                return Ok(stats);
+            }
+            Err(err) if output_failed || !self.is_recoverable(&err) => return Err(err),
+            Err(err) => err,
+        };
+
+        // A member is only counted once its header has been read.
+        let member = if self.stage == Stage::Header {
+            self.stats.members
+        } else {
+            self.stats.members - 1
+        };
+
+        output.write_chunk(&self.held)?;
+        recovered_size += self.held.len() as u64;
+        self.held.clear();
+        self.stage = Stage::Done;
+
+        self.stats.failure = Some(DecodeFailure {
+            member,
+            offset: self.consumed,
+            recovered_size,
+        });
+        self.options.warn(DecodeWarning::DecodeFailed {
+            offset: self.consumed,
+            error,
+        });
+        output.flush_output()?;
+
+        Ok(self.stats.clone())
+    }
+
+    /// Returns whether [`Decoder::decode_best_effort`] recovers from `err`, i.e. whether it
+    /// stems from damaged or truncated data rather than from the input not being lzip data at
+    /// all, or from something outside the data.
+    fn is_recoverable(&self, err: &LzipError) -> bool {
+        if self.stage == Stage::Header && self.stats.members == 0 {
+            return false;
+        }
+
+        match err {
+            LzipError::Corrupt { .. }
+            | LzipError::InvalidCrc
+            | LzipError::InvalidDataSize
+            | LzipError::InvalidMemberSize
+            | LzipError::UnexpectedEndOfStream
+            | LzipError::StreamError(_)
+            | LzipError::InvalidMagic
+            | LzipError::UnsupportedVersion
+            | LzipError::InvalidDictSize => true,
+            LzipError::IoError(err) => err.kind() == io::ErrorKind::UnexpectedEof,
+            LzipError::MemberError(_, err) => self.is_recoverable(err),
+            _ => false,
+        }
+    }
+
+    /// Turns the decoder into an iterator yielding the decompressed data of each member as its
+    /// own `Vec`.
+    ///
+    /// Every member is decoded, whether or not [`CONCATENATED`] is set, one per iteration: a
+    /// member's data is only yielded once its trailer has been verified. The LZMA stream is
+    /// rebuilt for each member, as the decoder does anyway. The first error ends the
+    /// iteration, and an empty input yields a single [`LzipError::UnexpectedEndOfStream`].
+    pub fn into_member_vecs(mut self) -> impl Iterator<Item = Result<Vec<u8>, LzipError>> {
+        self.options.lzma_flags &= !CONCATENATED;
+        let mut first = true;
+        let mut failed = false;
+
+        iter::from_fn(move || {
+            if failed {
+                return None;
+            }
+
+            let result = (|| {
+                if mem::take(&mut first) {
+                    if self.options.fill_buf(&mut self.input)?.is_empty() {
+                        return Err(LzipError::UnexpectedEndOfStream);
+                    }
+                } else if !self.next_member()? {
+                    return Ok(None);
+                }
+
+                let mut member = Vec::new();
+                self.decode_chunks(|chunk| {
+                    member.extend_from_slice(chunk);
+                    Ok(())
+                })?;
+
+                Ok(Some(member))
+            })();
+
+            failed = result.is_err();
+            result.transpose()
+        })
+    }
+
+    /// Decompresses the data from the input stream like [`Decoder::test`] and checks every
+    /// member against the corresponding entry of `expected`, e.g. a manifest kept alongside
+    /// the file.
+    ///
+    /// Beyond the corruption the trailers catch, this detects a valid file substituted for the
+    /// expected one. The members are compared in order, and the first mismatch is returned as
+    /// [`LzipError::MemberError`] with the member's index, wrapping [`LzipError::InvalidCrc`] or
+    /// [`LzipError::InvalidDataSize`]. A missing member is reported as
+    /// [`LzipError::UnexpectedEndOfStream`], and a member not in `expected` as
+    /// [`LzipError::InvalidDataSize`]. Decoding errors are returned as is, unless an earlier
+    /// member already mismatched. Remember to set [`CONCATENATED`] to check more than the first
+    /// member.
+    pub fn verify_against(&mut self, expected: &[MemberInfo]) -> Result<(), LzipError> {
+        self.member_infos = Some(Vec::new());
+        let result = self.test();
+        let actual = self.member_infos.take().unwrap_or_default();
+
+        for (index, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+            let error = if actual.crc32 != expected.crc32 {
+                LzipError::InvalidCrc
+            } else if actual.uncompressed_size != expected.uncompressed_size {
+                LzipError::InvalidDataSize
+            } else {
+                continue;
+            };
+
+            return Err(LzipError::MemberError(index as u64, Box::new(error)));
+        }
+        result?;
+
+        if actual.len() < expected.len() {
+            let error = Box::new(LzipError::UnexpectedEndOfStream);
+            return Err(LzipError::MemberError(actual.len() as u64, error));
+        }
+        if actual.len() > expected.len() {
+            let error = Box::new(LzipError::InvalidDataSize);
+            return Err(LzipError::MemberError(expected.len() as u64, error));
+        }
 
         Ok(())
     }
 
-    /// Reads the header from the input stream.
-    fn read_header(&mut self) -> Result<(), LzipError> {
-        let mut header = [0; 6];
-        self.input.read_exact(&mut header)?;
+    /// Decompresses the data from the input stream and writes it to every one of the sinks.
+    ///
+    /// The data is decompressed once and each chunk is written to the `sinks` in order, e.g. to
+    /// store the data while also feeding it to a separate hasher. The CRC32 is still verified.
+    pub fn decode_tee(&mut self, sinks: &mut [&mut dyn Write]) -> Result<DecodeStats, LzipError> {
+        self.decode_chunks(|chunk| {
+            for sink in sinks.iter_mut() {
+                sink.write_all(chunk)?;
+            }
+
+            Ok(())
+        })
+    }
+
+    /// Decompresses the data from the input stream and writes it to the output stream, passing
+    /// each chunk through `f` first.
+    ///
+    /// The CRC32 is computed on the decompressed data before `f` is applied, so the integrity of
+    /// the data is still verified while the output is transformed, e.g. to convert line endings.
+    pub fn decode_map<W, F>(&mut self, output: &mut W, mut f: F) -> Result<DecodeStats, LzipError>
+    where
+        W: Write,
+        F: FnMut(&[u8]) -> Vec<u8>,
+    {
+        self.decode_chunks(|chunk| Ok(output.write_all(&f(chunk))?))
+    }
+
+    /// Decompresses the data from the input stream and writes it to the output stream, hashing it
+    /// with `hasher` on the way.
+    ///
+    /// Each decompressed chunk is fed to both a CRC32 and the supplied digest, so a cryptographic
+    /// hash of the content is computed without a second pass over it. Returns the CRC32 of all
+    /// decompressed data along with the digest output. The CRC32 of each member is still verified.
+    ///
+    /// This method is only available with the `digest` feature.
+    #[cfg(feature = "digest")]
+    pub fn decode_verified<W, H>(
+        &mut self,
+        output: &mut W,
+        mut hasher: H,
+    ) -> Result<(u32, Vec<u8>), LzipError>
+    where
+        W: Write,
+        H: digest::Digest,
+    {
+        let mut crc32 = Hasher::new();
+        self.decode_chunks(|chunk| {
+            crc32.update(chunk);
+            hasher.update(chunk);
+            Ok(output.write_all(chunk)?)
+        })?;
+
+        Ok((crc32.finalize(), hasher.finalize().to_vec()))
+    }
+
+    /// Decompresses the data from the input stream, handing each decompressed chunk to `f` as
+    /// [`Bytes`](bytes::Bytes).
+    ///
+    /// The chunks are split off one shared `BytesMut` instead of each being copied into its own
+    /// allocation, so they can be passed on and shared cheaply, e.g. as HTTP body frames. Once
+    /// every chunk split off a block has been dropped, the block is reused for new chunks.
+    ///
+    /// This method is only available with the `bytes` feature.
+    #[cfg(feature = "bytes")]
+    pub fn decode_bytes<F>(&mut self, mut f: F) -> Result<DecodeStats, LzipError>
+    where
+        F: FnMut(bytes::Bytes) -> Result<(), LzipError>,
+    {
+        // Data already decompressed through `BufRead` but not consumed yet.
+        if self.pos < self.filled {
+            f(bytes::Bytes::copy_from_slice(
+                &self.buffer[self.pos..self.filled],
+            ))?;
+            self.pos = self.filled;
+        }
+
+        let mut buffer = bytes::BytesMut::new();
+        loop {
+            if buffer.capacity() < 4096 {
+                buffer.reserve(64 * 1024);
+            }
+            buffer.resize(4096, 0);
+
+            let written = self.read_decoded(&mut buffer)?;
+            if written == 0 {
+                break;
+            }
+
+            buffer.truncate(written);
+            f(buffer.split().freeze())?;
+        }
+
+        Ok(self.stats.clone())
+    }
+
+    /// Decompresses the data from the input stream through a caller supplied buffer.
+    ///
+    /// The decompressed data is written into `buf` and `cb` is called with its contents whenever
+    /// it is full, and once more with the remaining data at the end. This bounds the memory used
+    /// for output to the size of `buf`, however large the decompressed data is. With
+    /// [`DecodeOptions::window_overlap`], each call after the first repeats the end of the
+    /// previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`LzipError::IoError`] of kind [`io::ErrorKind::InvalidInput`] if `buf` is
+    /// empty or not larger than the [`DecodeOptions::window_overlap`], besides the errors of
+    /// decoding and those returned by `cb`.
+    pub fn decode_with_callback<F>(
+        &mut self,
+        buf: &mut [u8],
+        mut cb: F,
+    ) -> Result<DecodeStats, LzipError>
+    where
+        F: FnMut(&[u8]) -> Result<(), LzipError>,
+    {
+        if buf.is_empty() {
+            return Err(
+                io::Error::new(io::ErrorKind::InvalidInput, "buffer must not be empty").into(),
+            );
+        }
+        let overlap = self.options.window_overlap;
+        if overlap >= buf.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "buffer must be larger than the window overlap",
+            )
+            .into());
+        }
+
+        // The bytes at the start of `buf` repeated from the previous window.
+        let mut carry = 0;
+
+        // Data already decompressed through `BufRead` but not consumed yet.
+        if self.pos < self.filled {
+            let pending = &self.buffer[self.pos..self.filled];
+            cb(pending)?;
+            carry = pending.len().min(overlap);
+            buf[..carry].copy_from_slice(&pending[pending.len() - carry..]);
+            self.pos = self.filled;
+        }
+
+        loop {
+            let mut filled = carry;
+            while filled < buf.len() {
+                let written = self.read_decoded(&mut buf[filled..])?;
+                if written == 0 {
+                    break;
+                }
+                filled += written;
+            }
+
+            if filled > carry {
+                cb(&buf[..filled])?;
+            }
+
+            if filled < buf.len() {
+                break;
+            }
+
+            carry = overlap;
+            buf.copy_within(filled - carry..filled, 0);
+        }
+
+        Ok(self.stats.clone())
+    }
+
+    /// Decompresses all remaining data, handing each decompressed chunk to `f`.
+    pub(crate) fn decode_chunks<F>(&mut self, mut f: F) -> Result<DecodeStats, LzipError>
+    where
+        F: FnMut(&[u8]) -> Result<(), LzipError>,
+    {
+        // Data already decompressed through `BufRead` but not consumed yet.
+        if self.pos < self.filled {
+            f(&self.buffer[self.pos..self.filled])?;
+            self.pos = self.filled;
+        }
+
+        let mut output_buf = [0u8; 4096];
+
+        loop {
+            let written = self.read_decoded(&mut output_buf)?;
+            if written == 0 {
+                break;
+            }
+
+            f(&output_buf[..written])?;
+        }
+
+        Ok(self.stats.clone())
+    }
+
+    /// Returns the statistics collected so far.
+    pub fn stats(&self) -> &DecodeStats {
+        &self.stats
+    }
+
+    /// Returns the trailer of the most recently decoded member, or `None` if no trailer has
+    /// been read yet.
+    ///
+    /// The fields are the values stored in the input rather than the recomputed ones, and are
+    /// recorded before being verified, so they can be compared against the decoded data in
+    /// diagnostics. A [`TrailerFormat::Version0`] trailer stores no member size; the computed
+    /// one is filled in instead.
+    pub fn last_trailer(&self) -> Option<Trailer> {
+        self.trailers.last().copied()
+    }
+
+    /// Returns the trailers of all members decoded so far, in input order.
+    ///
+    /// See [`Decoder::last_trailer`] for what the fields hold.
+    pub fn trailers(&self) -> &[Trailer] {
+        &self.trailers
+    }
+
+    /// Returns the number of compressed bytes consumed from the input so far.
+    ///
+    /// Headers and trailers are counted along with the LZMA data, so once every member has
+    /// been decoded this equals the size of the compressed input. Together with a known input
+    /// size, this shows progress on the compressed side before the uncompressed size is known.
+    pub fn compressed_bytes_consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Validates the header without decompressing any data and returns the dictionary size.
+    ///
+    /// Only the 6 header bytes are read from the input and no LZMA state is allocated, which makes
+    /// this a cheap way to reject invalid data before committing to a full decode. A subsequent
+    /// call to [`Decoder::decode`] continues from the already validated header.
+    pub fn validate_header_only(&mut self) -> Result<u32, LzipError> {
+        if self.stage == Stage::Header {
+            self.read_header()?;
+        }
+
+        Ok(self.dict_size)
+    }
+
+    /// Returns the current state of the decoder.
+    pub fn state(&self) -> DecoderState {
+        match self.stage {
+            Stage::Header => DecoderState::ReadingHeader,
+            Stage::Data => DecoderState::Decompressing,
+            Stage::Trailer => DecoderState::ReadingTrailer,
+            Stage::Done => DecoderState::Done,
+        }
+    }
+
+    /// Performs a single step of decoding and returns the state the decoder is in afterwards.
+    ///
+    /// A step reads a member header, decompresses one chunk of data to `output`, or reads and
+    /// verifies a member trailer, so a decode can be driven from an event loop with other work
+    /// interleaved between steps. Data held back by [`MemberErrorPolicy::Skip`] is written once
+    /// the trailer of its member has been verified. Stepping a decoder in
+    /// [`DecoderState::Done`] does nothing. Unlike [`Decoder::decode`], `output` is not flushed.
+    pub fn step<W: Write + ?Sized>(&mut self, output: &mut W) -> Result<DecoderState, LzipError> {
+        // Data already decompressed through `BufRead` but not consumed yet.
+        if self.pos < self.filled {
+            output.write_all(&self.buffer[self.pos..self.filled])?;
+            self.pos = self.filled;
+        }
+
+        let mut output_buf = [0u8; 4096];
+        if let Some(written) = self.advance(&mut output_buf)? {
+            output.write_all(&output_buf[..written])?;
+        }
+
+        if self.ready_pos < self.ready.len() {
+            output.write_all(&self.ready[self.ready_pos..])?;
+            self.ready_pos = self.ready.len();
+        }
+
+        Ok(self.state())
+    }
+
+    /// Decompresses the next chunk of data into `buf`, moving through members as needed.
+    ///
+    /// Returns the number of bytes written to `buf`, which is only zero once every member has
+    /// been decoded (or `buf` is empty), or once the pushed input runs out.
+    fn read_decoded(&mut self, buf: &mut [u8]) -> Result<usize, LzipError> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        loop {
+            if self.ready_pos < self.ready.len() {
+                let n = (self.ready.len() - self.ready_pos).min(buf.len());
+                buf[..n].copy_from_slice(&self.ready[self.ready_pos..self.ready_pos + n]);
+                self.ready_pos += n;
+                return Ok(n);
+            }
 
-        if header[0..4] != LZIP_MAGIC {
+            if let Some(written) = self.advance(buf)? {
+                return Ok(written);
+            }
+        }
+    }
+
+    /// Moves the decoder one stage further: reads a header, decompresses a chunk into `buf`,
+    /// or reads a trailer.
+    ///
+    /// Returns the number of bytes written to `buf` if [`Decoder::read_decoded`] should return
+    /// them, or `None` if it should carry on.
+    fn advance(&mut self, buf: &mut [u8]) -> Result<Option<usize>, LzipError> {
+        let policy = self.options.member_error_policy;
+
+        match self.stage {
+            Stage::Header => {
+                if !self.has_input(Header::SIZE) {
+                    return Ok(Some(0));
+                }
+                self.read_header()?
+            }
+            Stage::Data => {
+                let written = self.decompress(buf)?;
+                if written == 0 && self.stage == Stage::Data {
+                    return Ok(Some(0));
+                }
+                if policy == MemberErrorPolicy::Skip {
+                    if self.held.capacity() == 0 {
+                        self.held = self.options.take_buffer();
+                    }
+                    self.held.extend_from_slice(&buf[..written]);
+                } else if written > 0 {
+                    return Ok(Some(written));
+                }
+            }
+            Stage::Trailer => {
+                if self.external_trailer.is_none()
+                    && !self.raw
+                    && !self.has_input(self.options.trailer_format.size())
+                {
+                    return Ok(Some(0));
+                }
+
+                match self.read_trailer() {
+                    Ok(()) => {
+                        mem::swap(&mut self.ready, &mut self.held);
+                        self.held.clear();
+                        self.ready_pos = 0;
+                    }
+                    Err(
+                        error @ (LzipError::InvalidCrc
+                        | LzipError::InvalidDataSize
+                        | LzipError::InvalidMemberSize),
+                    ) if policy != MemberErrorPolicy::Abort => {
+                        let index = self.stats.members - 1;
+                        self.stats.failed_members.push(index);
+                        self.held.clear();
+
+                        self.options.warn(if policy == MemberErrorPolicy::Skip {
+                            DecodeWarning::MemberSkipped { index, error }
+                        } else {
+                            DecodeWarning::MemberEmitted { index, error }
+                        });
+                    }
+                    Err(
+                        error @ (LzipError::InvalidCrc
+                        | LzipError::InvalidDataSize
+                        | LzipError::InvalidMemberSize),
+                    ) => {
+                        return Err(LzipError::Corrupt {
+                            member: self.stats.members - 1,
+                            offset: self.data_start + self.compressed_size,
+                            error: Box::new(error),
+                        });
+                    }
+                    Err(err) => return Err(err),
+                }
+
+                if let Some(member_infos) = &mut self.member_infos {
+                    member_infos.push(MemberInfo {
+                        crc32: self.crc32,
+                        uncompressed_size: self.uncompressed_size,
+                    });
+                }
+
+                self.stage = if self.has_more()? {
+                    Stage::Header
+                } else {
+                    Stage::Done
+                };
+            }
+            Stage::Done => return Ok(Some(0)),
+        }
+
+        Ok(None)
+    }
+
+    /// Moves on to the next member once the current one has been decoded without
+    /// [`CONCATENATED`].
+    ///
+    /// Returns `false` if the input has no more data.
+    pub(crate) fn next_member(&mut self) -> Result<bool, LzipError> {
+        if self.stage != Stage::Done || self.raw || self.external_trailer.is_some() {
+            return Ok(false);
+        }
+
+        while self.skip_index()? {}
+        if self.options.fill_buf(&mut self.input)?.is_empty() {
+            return Ok(false);
+        }
+
+        self.stage = Stage::Header;
+        Ok(true)
+    }
+
+    /// Returns whether another member follows the one just finished.
+    fn has_more(&mut self) -> Result<bool, LzipError> {
+        Ok(self.options.has_flag(CONCATENATED)
+            && self.external_trailer.is_none()
+            && !self.raw
+            && (self.options.follow
+                || self.pushed.is_some()
+                || !self.options.fill_buf(&mut self.input)?.is_empty()))
+    }
+
+    /// Returns whether `len` more bytes can be read without running out of pushed input.
+    ///
+    /// Always `true` unless the input is fed through [`Decoder::push_frame`].
+    fn has_input(&self, len: usize) -> bool {
+        self.pushed
+            .is_none_or(|pushed| pushed - self.consumed >= len as u64)
+    }
+
+    /// Skips a trailing [index](crate::index) if one starts at the current position.
+    ///
+    /// Returns `false` without consuming anything if the next byte doesn't start an index.
+    fn skip_index(&mut self) -> Result<bool, LzipError> {
+        if self.options.fill_buf(&mut self.input)?.first() != Some(&INDEX_MAGIC[0]) {
+            return Ok(false);
+        }
+
+        let mut head = [0; INDEX_MAGIC.len() + 8];
+        self.read_input(&mut head[..INDEX_MAGIC.len()])?;
+        if head[..INDEX_MAGIC.len()] != INDEX_MAGIC {
             return Err(LzipError::InvalidMagic);
         }
 
-        if header[4] != LZIP_VERSION {
-            return Err(LzipError::UnsupportedVersion);
+        // The rest of a pushed index may still be in flight, so it is dropped as it arrives.
+        if self.pushed.is_some() {
+            self.consumed += INDEX_MAGIC.len() as u64;
+            self.consumed += io::copy(&mut self.input, &mut io::sink())?;
+            return Ok(true);
         }
 
-        self.dict_size = Self::decode_dict_size(header[5])?;
-        if self.dict_size < MIN_DICT_SIZE || self.dict_size > MAX_DICT_SIZE {
-            return Err(LzipError::InvalidDictSize);
+        self.read_input(&mut head[INDEX_MAGIC.len()..])?;
+
+        let count = u64::from_le_bytes(head[INDEX_MAGIC.len()..].try_into().unwrap());
+        let size = index_body_size(count).ok_or(LzipError::InvalidMemberSize)?;
+        let skipped = io::copy(&mut self.input.by_ref().take(size), &mut io::sink())?;
+        if skipped != size {
+            return Err(LzipError::UnexpectedEndOfStream);
         }
+        self.consumed += head.len() as u64 + size;
+
+        Ok(true)
+    }
+
+    /// Reads the header from the input stream.
+    ///
+    /// A trailing [index](crate::index) after a member is skipped instead, ending the input
+    /// unless more members follow it. A pushed index always ends the input.
+    fn read_header(&mut self) -> Result<(), LzipError> {
+        if self.stats.members > 0 && !self.raw && self.skip_index()? {
+            self.stage = if self.pushed.is_none() && self.has_more()? {
+                Stage::Header
+            } else {
+                Stage::Done
+            };
+            return Ok(());
+        }
+
+        if let Some(dict_size) = self.options.assume_raw
+            && self.stats.members == 0
+        {
+            let input = self.options.fill_buf(&mut self.input)?;
+            let len = input.len().min(LZIP_MAGIC.len());
+            if input[..len] != LZIP_MAGIC[..len] {
+                if !(MIN_DICT_SIZE..=MAX_DICT_SIZE).contains(&dict_size) {
+                    return Err(LzipError::InvalidDictSize);
+                }
+
+                self.raw = true;
+                self.begin_member(dict_size);
+                return Ok(());
+            }
+        }
+
+        let mut header = [0; Header::SIZE];
+        self.read_input(&mut header)?;
+        self.consumed += Header::SIZE as u64;
+
+        let dict_size = Header::parse(&header, self.options.trailer_format)?.dict_size;
+        self.begin_member(dict_size);
 
         Ok(())
     }
 
-    /// Decompress and write the data to the output stream.
-    fn decompress<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
-        let mut options = LzmaOptions::new_preset(LZMA_PRESET_DEFAULT)?;
-        options.dict_size(self.dict_size);
+    /// Fills `buf` from the input, waiting for more data at the end of a followed input.
+    fn read_input(&mut self, buf: &mut [u8]) -> Result<(), LzipError> {
+        let mut filled = 0;
+        while filled < buf.len() {
+            let input = self.options.fill_buf(&mut self.input)?;
+            if input.is_empty() {
+                if !self.options.follow {
+                    return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+                }
+                self.options.poll()?;
+                continue;
+            }
+
+            let n = input.len().min(buf.len() - filled);
+            buf[filled..filled + n].copy_from_slice(&input[..n]);
+            self.input.consume(n);
+            filled += n;
+        }
+
+        Ok(())
+    }
 
-        let mut filters = Filters::new();
-        filters.lzma1(&options);
+    /// Moves on to the data of a new member with the given dictionary size.
+    fn begin_member(&mut self, dict_size: u32) {
+        self.dict_size = dict_size;
+        self.data_start = self.consumed;
+        self.stage = Stage::Data;
+        self.stats.members += 1;
 
-        let mut stream = Stream::new_raw_decoder(&filters)?;
+        let level = level_for_dict_size(self.dict_size);
+        if self.stats.members == 1 || self.stats.inferred_level == level {
+            self.stats.inferred_level = level;
+        } else {
+            self.stats.inferred_level = None;
+        }
+    }
 
-        let mut output_buf = [0u8; 4096];
-        let mut hasher = Hasher::new();
+    /// Decompress the next chunk of the current member into `buf`.
+    ///
+    /// Returns the number of bytes written to `buf`. Once the end of the member's data is
+    /// reached, the decoder moves on to the trailer.
+    fn decompress(&mut self, buf: &mut [u8]) -> Result<usize, LzipError> {
+        // Each member declares its own dictionary size, so a fresh stream is built per member.
+        // A finished stream can't be reset through liblzma's bindings, and caching the filter
+        // chain would make the decoder `!Send`; creating a stream costs a few microseconds.
+        if self.stream.is_none() {
+            self.reservation = None;
+            if let Some(budget) = &self.options.memory_budget {
+                let usage =
+                    decoder_memusage(self.dict_size)?.saturating_add(self.input.capacity() as u64);
+                self.reservation = Some(budget.reserve(usage)?);
+            }
+            self.stream = Some(new_raw_decoder(self.dict_size, self.options.lzma_params)?);
+            self.hasher = Hasher::new();
+        }
+        let stream = self.stream.as_mut().unwrap();
 
         loop {
-            let input_buf = self.input.fill_buf()?;
+            if self.options.is_cancelled() {
+                return Err(LzipError::Cancelled);
+            }
+
+            let input_buf = self.options.fill_buf(&mut self.input)?;
             let eof = input_buf.is_empty();
+            let finish = eof && !self.options.follow && self.pushed.is_none();
 
             let before_out = stream.total_out();
             let before_in = stream.total_in();
 
-            let status = stream.process(
-                input_buf,
-                &mut output_buf,
-                if eof { Action::Finish } else { Action::Run },
-            )?;
-            let read = (stream.total_in() - before_in) as usize;
-            let written = (stream.total_out() - before_out) as usize;
+            let status = stream
+                .process(
+                    input_buf,
+                    buf,
+                    if finish { Action::Finish } else { Action::Run },
+                )
+                .map_err(|err| LzipError::Corrupt {
+                    member: self.stats.members - 1,
+                    offset: self.data_start + stream.total_in(),
+                    error: Box::new(err.into()),
+                })?;
+            let read = processed(before_in, stream.total_in(), input_buf.len())?;
+            let written = processed(before_out, stream.total_out(), buf.len())?;
 
             self.input.consume(read);
 
-            output.write_all(&output_buf[..written])?;
-            hasher.update(&output_buf[..written]);
+            if !self.options.structural_only {
+                self.hasher.update(&buf[..written]);
+            }
+
+            self.total_in += read as u64;
+            self.consumed += read as u64;
+            self.total_out += written as u64;
+            if let Some(max_ratio) = self.options.max_ratio
+                && self.total_in >= MIN_RATIO_INPUT
+                && self.total_out as f64 / self.total_in as f64 > max_ratio
+            {
+                return Err(LzipError::RatioExceeded);
+            }
 
             if status == Status::StreamEnd {
-                self.crc32 = hasher.finalize();
+                self.crc32 = mem::take(&mut self.hasher).finalize();
                 self.uncompressed_size = stream.total_out();
                 self.compressed_size = stream.total_in();
-                break;
+                self.stream = None;
+                self.reservation = None;
+                self.stage = Stage::Trailer;
+                return Ok(written);
             }
 
+            // The final flush can span many `Finish` calls once the input is exhausted, so the
+            // stream is only truncated if a call at the end of input produced no output at all.
             if eof && written == 0 {
-                return Err(LzipError::UnexpectedEndOfStream);
+                if self.pushed.is_some() {
+                    return Ok(0);
+                }
+                if !self.options.follow {
+                    return Err(LzipError::UnexpectedEndOfStream);
+                }
+                self.options.poll()?;
             }
-        }
 
-        Ok(())
+            if written > 0 {
+                return Ok(written);
+            }
+        }
     }
 
     /// Reads the trailer from the input stream.
     fn read_trailer(&mut self) -> Result<(), LzipError> {
-        let mut trailer = [0; 20];
-        self.input.read_exact(&mut trailer)?;
+        if self.raw {
+            return Ok(());
+        }
+
+        let trailer = match self.external_trailer {
+            // A headerless payload has no member size to verify.
+            Some(trailer) => Trailer {
+                member_size: member_size(self.compressed_size),
+                ..trailer
+            },
+            None => {
+                let format = self.options.trailer_format;
+                let mut trailer = [0; Trailer::SIZE];
+                self.read_input(&mut trailer[..format.size()])?;
+                self.consumed += format.size() as u64;
+
+                let mut trailer = Trailer::from_bytes(&trailer);
+                if format == TrailerFormat::Version0 {
+                    trailer.member_size = member_size(self.compressed_size);
+                }
+                trailer
+            }
+        };
+        self.trailers.push(trailer);
 
-        let crc32 = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
-        if crc32 != self.crc32 {
-            return Err(LzipError::InvalidCrc);
+        if trailer.crc32 != self.crc32 && !self.options.structural_only {
+            if !self.options.has_flag(IGNORE_CHECK) {
+                return Err(LzipError::InvalidCrc);
+            }
+
+            self.options.warn(DecodeWarning::CrcMismatch {
+                member: self.stats.members - 1,
+                expected: trailer.crc32,
+                got: self.crc32,
+            });
         }
 
-        let uncompressed_size = u64::from_le_bytes(trailer[4..12].try_into().unwrap());
-        if uncompressed_size != self.uncompressed_size {
+        if trailer.uncompressed_size != self.uncompressed_size {
             return Err(LzipError::InvalidDataSize);
         }
 
-        let member_size = u64::from_le_bytes(trailer[12..20].try_into().unwrap());
-        // 6 bytes for header, 20 bytes for trailer
-        if member_size != (6 + self.compressed_size + 20) {
+        if trailer.member_size != member_size(self.compressed_size) {
             return Err(LzipError::InvalidMemberSize);
         }
 
         Ok(())
     }
+}
+
+impl<R: Read + Seek> Decoder<R> {
+    /// Creates a new `Decoder` instance for lzip data starting `skip` bytes past the current
+    /// position of `input`, seeking over the skipped bytes.
+    ///
+    /// This is [`Decoder::with_offset`] for seekable inputs. Seeking past the end isn't
+    /// detected here; decoding then fails when reading the header.
+    pub fn with_offset_seekable(mut input: R, skip: u64) -> Result<Self, LzipError> {
+        let skip = i64::try_from(skip).map_err(|_| LzipError::UnexpectedEndOfStream)?;
+        input.seek(SeekFrom::Current(skip))?;
+
+        Ok(Self::new(input))
+    }
 
-    /// Decodes the given byte to a dictionary size value.
-    fn decode_dict_size(dict_size: u8) -> Result<u32, LzipError> {
-        let mut ds: u32 = 1 << (dict_size & 0x1F);
-        if ds > MIN_DICT_SIZE {
-            ds -= (ds / 16) * (((dict_size as u32) >> 5) & 0x07);
+    /// Returns the total uncompressed size of all members without decompressing them.
+    ///
+    /// The members are walked backwards from the end of the input using the member size stored
+    /// in each trailer, like `lzip --list` does, and their uncompressed sizes are summed. The
+    /// input position is restored afterwards, so a subsequent decode is unaffected. Returns
+    /// [`LzipError::InvalidMemberSize`] if the members don't line up with the input.
+    pub fn total_uncompressed_size(&mut self) -> Result<u64, LzipError> {
+        walk_members(&mut self.input)?
+            .iter()
+            .try_fold(0u64, |total, member| {
+                total.checked_add(member.uncompressed_size)
+            })
+            .ok_or(LzipError::InvalidDataSize)
+    }
+
+    /// Returns the uncompressed size of the first member from the current position, without
+    /// decompressing it.
+    ///
+    /// The member size needed to find a member's trailer is stored in that trailer, so the
+    /// first member can't be found from the front. It is located like [`Decoder::read_index`]
+    /// does instead, through the trailing index if there is one and otherwise by walking the
+    /// members backwards, which only reads their trailers. The input position is restored
+    /// afterwards, so a subsequent decode is unaffected. Returns
+    /// [`LzipError::UnexpectedEndOfStream`] if the input has no members.
+    pub fn first_member_size(&mut self) -> Result<u64, LzipError> {
+        self.read_index()?
+            .first()
+            .map(|entry| entry.uncompressed_size)
+            .ok_or(LzipError::UnexpectedEndOfStream)
+    }
+
+    /// Returns the location and size of every member from the current position to the end of
+    /// the input.
+    ///
+    /// The [index](crate::index) written with [`EncoderOptions::append_index`] is read if the
+    /// input ends with one, otherwise the members are walked like
+    /// [`Decoder::total_uncompressed_size`] does. Offsets are relative to the current position,
+    /// so a member can be decoded on its own by seeking to it. The input position is restored
+    /// afterwards.
+    ///
+    /// [`EncoderOptions::append_index`]: crate::EncoderOptions::append_index
+    pub fn read_index(&mut self) -> Result<Vec<IndexEntry>, LzipError> {
+        let start = self.input.stream_position()?;
+        let index = read_index(&mut self.input, start);
+        self.input.seek(SeekFrom::Start(start))?;
+
+        if let Some(entries) = index? {
+            return Ok(entries);
         }
 
-        Ok(ds)
+        let entries = walk_members(&mut self.input)?
+            .iter()
+            .map(|member| IndexEntry {
+                offset: member.offset,
+                member_size: member.member_size,
+                uncompressed_size: member.uncompressed_size,
+            })
+            .collect();
+        Ok(entries)
+    }
+
+    /// Checks whether the input holds complete lzip members up to its end, without
+    /// decompressing them.
+    ///
+    /// The header at the current position is parsed, then the members are walked like
+    /// [`Decoder::total_uncompressed_size`] does and must line up exactly with the input. This
+    /// cheaply detects a file that is still being written or was cut short, in which case
+    /// `false` is returned. A malformed first header is reported as an error instead. The
+    /// compressed data and CRCs are not checked, which only a full decode does. The input
+    /// position is restored afterwards.
+    pub fn is_complete(&mut self) -> Result<bool, LzipError> {
+        let start = self.input.stream_position()?;
+        let mut header = [0; Header::SIZE];
+        let read = self.input.read_exact(&mut header);
+        self.input.seek(SeekFrom::Start(start))?;
+
+        match read {
+            Ok(()) => Header::from_bytes(&header)?,
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(false),
+            Err(err) => return Err(err.into()),
+        };
+
+        match walk_members(&mut self.input) {
+            Ok(_) => Ok(true),
+            Err(LzipError::InvalidMemberSize) => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Decompresses the data from the input stream into a newly created file at `path`.
+    ///
+    /// The total uncompressed size is read from the trailers first and the file is extended to
+    /// it up front with [`File::set_len`], so it doesn't grow with every write. If the size
+    /// can't be determined, e.g. because the input can't actually seek, the data is streamed
+    /// into the file as is. Either way the file is truncated to the decompressed data in the
+    /// end, also when decoding fails, so a failed decode never leaves a file of the full size
+    /// behind. An existing file at `path` is overwritten.
+    pub fn decode_to_file(&mut self, path: &Path) -> Result<DecodeStats, LzipError> {
+        let file = File::create(path)?;
+        if self.stage == Stage::Header
+            && let Ok(size) = self.total_uncompressed_size()
+        {
+            file.set_len(size)?;
+        }
+
+        let mut output = BufWriter::new(file);
+        let mut written: u64 = 0;
+        let result = self.decode_chunks(|chunk| {
+            output.write_all(chunk)?;
+            written += chunk.len() as u64;
+            Ok(())
+        });
+
+        // The preallocated tail would otherwise read as zeros of a complete looking file.
+        let truncated = output
+            .into_inner()
+            .map_err(|err| err.into_error())
+            .and_then(|file| file.set_len(written));
+        let stats = result?;
+        truncated?;
+
+        Ok(stats)
+    }
+
+    /// Verifies every member against its trailer, decompressing up to `threads` members in
+    /// parallel and discarding the output.
+    ///
+    /// The members are found like [`Decoder::total_uncompressed_size`] does, and the input
+    /// position is restored afterwards, so this should be called before decoding. Each member
+    /// is decompressed on its own thread, which speeds up testing large multi-member files on
+    /// multi-core machines. The calling thread reads the members in turn and hands them to
+    /// their threads a chunk at a time, so only a few chunks per thread are held in memory
+    /// however large the members are. With a single member or thread, the members are verified
+    /// one after the other on the calling thread, streaming from the input like
+    /// [`Decoder::test`].
+    ///
+    /// Returns [`LzipError::Corrupt`] for the first member with corrupt data, or
+    /// [`LzipError::MemberError`] with its index for any other failure.
+    pub fn verify_parallel(&mut self, threads: usize) -> Result<(), LzipError> {
+        let start = self.input.stream_position()?;
+        let members = walk_members(&mut self.input)?;
+        let result = self.verify_members(start, &members, threads.max(1));
+        self.input.seek(SeekFrom::Start(start))?;
+
+        result
+    }
+
+    /// Verifies the given members in batches of `threads` members.
+    fn verify_members(
+        &mut self,
+        start: u64,
+        members: &[MemberEntry],
+        threads: usize,
+    ) -> Result<(), LzipError> {
+        let options = self
+            .options
+            .clone()
+            .lzma_flags(self.options.lzma_flags & !CONCATENATED);
+        let verify = |index: usize, input: &mut dyn Read| {
+            Decoder::new_with_options(input, options.clone())
+                .test()
+                .map(|_| ())
+                .map_err(|err| match err {
+                    // Each member is decoded on its own, so the offset is rebased onto the input.
+                    LzipError::Corrupt { offset, error, .. } => LzipError::Corrupt {
+                        member: index as u64,
+                        offset: members[index].offset + offset,
+                        error,
+                    },
+                    err => LzipError::MemberError(index as u64, Box::new(err)),
+                })
+        };
+
+        for (batch, chunk) in members.chunks(threads).enumerate() {
+            let first = batch * threads;
+            if let [member] = chunk {
+                self.input.seek(SeekFrom::Start(start + member.offset))?;
+                verify(first, &mut (&mut self.input).take(member.member_size))?;
+                continue;
+            }
+
+            let results: Vec<Result<(), LzipError>> = thread::scope(|scope| {
+                let mut handles = Vec::with_capacity(chunk.len());
+                let mut feeds = Vec::with_capacity(chunk.len());
+                for (i, member) in chunk.iter().enumerate() {
+                    let (sender, receiver) = mpsc::sync_channel::<Vec<u8>>(VERIFY_QUEUE_LEN);
+                    handles.push(scope.spawn(move || {
+                        let mut reader = ResultIterReader::new(receiver.into_iter().map(Ok));
+                        verify(first + i, &mut reader)
+                    }));
+                    feeds.push((sender, start + member.offset, member.member_size));
+                }
+
+                // The senders are dropped once fed or on error, ending the threads' input.
+                self.feed_members(feeds)?;
+
+                Ok::<_, LzipError>(
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().expect("verifier thread panicked"))
+                        .collect(),
+                )
+            })?;
+            results.into_iter().collect::<Result<(), LzipError>>()?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads the members described by `feeds`, each a sender with the offset and size of a
+    /// member, and sends each its member a chunk at a time, going around the members in turn.
+    fn feed_members(
+        &mut self,
+        mut feeds: Vec<(SyncSender<Vec<u8>>, u64, u64)>,
+    ) -> Result<(), LzipError> {
+        while !feeds.is_empty() {
+            let mut i = 0;
+            while i < feeds.len() {
+                let (sender, offset, remaining) = &mut feeds[i];
+                let len = (*remaining).min(VERIFY_CHUNK_SIZE as u64);
+                let mut chunk = vec![0; len as usize];
+                self.input.seek(SeekFrom::Start(*offset))?;
+                self.input.read_exact(&mut chunk)?;
+                *offset += len;
+                *remaining -= len;
+
+                // A thread stops receiving once its member failed, which it reports when joined.
+                if sender.send(chunk).is_err() || *remaining == 0 {
+                    feeds.swap_remove(i);
+                } else {
+                    i += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<I: Iterator<Item = io::Result<Vec<u8>>>> Decoder<ResultIterReader<I>> {
+    /// Creates a new `Decoder` instance reading its input from an iterator of chunks.
+    ///
+    /// Each chunk is fed to the decoder as it is pulled from `iter`, so the compressed data is
+    /// never collected up front. An `Err` yielded by the iterator fails decoding with
+    /// [`LzipError::IoError`]. Use [`Decoder::new_with_options`] with a [`ResultIterReader`] to
+    /// decode with custom options.
+    pub fn from_result_iter(iter: I) -> Self {
+        Self::new(ResultIterReader::new(iter))
+    }
+}
+
+impl Decoder<FrameInput> {
+    /// Creates a new `Decoder` instance whose input is pushed frame by frame through
+    /// [`Decoder::push_frame`], e.g. as packets arrive from a transport.
+    pub fn new_framed(options: DecodeOptions) -> Self {
+        let mut decoder = Self::new_with_options(FrameInput::default(), options);
+        decoder.pushed = Some(0);
+        decoder
+    }
+
+    /// Feeds the next frame of compressed data to the decoder and writes everything it
+    /// decompresses to `output`.
+    ///
+    /// Frames may split the data anywhere, including inside a header or trailer, and may be
+    /// empty. The whole frame is taken; bytes the decoder can't use yet are kept for the next
+    /// call. The returned [`DecodeProgress`] tells whether the data pushed so far ends in the
+    /// middle of a member, on a member boundary, or at the end of the stream. With
+    /// [`CONCATENATED`], more members may always follow, so the transport decides where the
+    /// stream ends: it ended cleanly if its last frame returned
+    /// [`DecodeProgress::MemberComplete`].
+    ///
+    /// Frames pushed after [`DecodeProgress::StreamEnd`] are ignored.
+    pub fn push_frame<W: Write + ?Sized>(
+        &mut self,
+        frame: &[u8],
+        output: &mut W,
+    ) -> Result<DecodeProgress, LzipError> {
+        if self.stage != Stage::Done {
+            self.input.get_mut().push(frame);
+            self.pushed = self.pushed.map(|pushed| pushed + frame.len() as u64);
+        }
+
+        self.decode_chunks(|chunk| Ok(output.write_all(chunk)?))?;
+
+        Ok(match self.stage {
+            Stage::Done => DecodeProgress::StreamEnd,
+            Stage::Header if self.stats.members > 0 && !self.has_input(1) => {
+                DecodeProgress::MemberComplete
+            }
+            _ => DecodeProgress::NeedMore,
+        })
+    }
+}
+
+/// The state of a [`Decoder`] fed through [`Decoder::push_frame`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodeProgress {
+    /// The data pushed so far ends inside a member, so another frame is needed.
+    NeedMore,
+    /// The data pushed so far ends right after a complete member.
+    MemberComplete,
+    /// The stream has ended: the single member without [`CONCATENATED`], or a trailing
+    /// [index](crate::index), has been decoded.
+    StreamEnd,
+}
+
+/// The queue of pushed frames a [`Decoder::new_framed`] decoder reads from.
+///
+/// Reading from an empty queue returns zero bytes, which the decoder takes as a request for
+/// another frame rather than the end of its input.
+#[derive(Debug, Default)]
+pub struct FrameInput {
+    /// The pushed bytes.
+    data: Vec<u8>,
+    /// The position of the first unread byte in `data`.
+    pos: usize,
+}
+
+impl FrameInput {
+    /// Appends a frame, dropping the bytes read already.
+    fn push(&mut self, frame: &[u8]) {
+        self.data.drain(..self.pos);
+        self.pos = 0;
+        self.data.extend_from_slice(frame);
+    }
+}
+
+impl Read for FrameInput {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = (self.data.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.data[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+/// A reader pulling byte buffers from an iterator of `io::Result`s.
+///
+/// The reader reaches end of file once the iterator is exhausted. An `Err` yielded by the
+/// iterator is returned from `read` as is.
+pub struct ResultIterReader<I> {
+    /// The iterator to pull buffers from.
+    iter: I,
+    /// The buffer currently being read.
+    chunk: Vec<u8>,
+    /// The position of the first unread byte in `chunk`.
+    pos: usize,
+}
+
+impl<I: Iterator<Item = io::Result<Vec<u8>>>> ResultIterReader<I> {
+    /// Creates a new `ResultIterReader` pulling from `iter`.
+    pub fn new(iter: I) -> Self {
+        ResultIterReader {
+            iter,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl<I: Iterator<Item = io::Result<Vec<u8>>>> Read for ResultIterReader<I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match self.iter.next() {
+                Some(chunk) => {
+                    self.chunk = chunk?;
+                    self.pos = 0;
+                }
+                None => return Ok(0),
+            }
+        }
+
+        let n = (self.chunk.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
+    }
+}
+
+/// The input of a [`MaybeDecoder`]: the sniffed prefix followed by the rest of the input.
+type SniffedInput<R> = io::Chain<io::Cursor<Vec<u8>>, R>;
+
+/// A reader which decompresses its input if it is lzip data, and passes it through unchanged
+/// otherwise.
+///
+/// The first 4 bytes of the input are read up front and compared with the lzip magic. lzip
+/// data is read through a [`Decoder`], anything else, including input shorter than the magic,
+/// is returned byte for byte, the sniffed prefix included.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::{Encoder, MaybeDecoder};
+/// use std::io::Read;
+///
+/// let input = b"the quick brown fox jumps over the lazy dog";
+/// let encoded = Encoder::new(input.as_slice())
+///     .finish_to_vec()
+///     .expect("failed to encode");
+///
+/// for data in [encoded.as_slice(), input.as_slice()] {
+///     let mut decoded = Vec::new();
+///     let mut reader = MaybeDecoder::new(data).expect("failed to sniff");
+///     reader.read_to_end(&mut decoded).expect("failed to read");
+///     assert_eq!(decoded, input);
+/// }
+/// ```
+pub enum MaybeDecoder<R: Read> {
+    /// The input is lzip data, decompressed through a decoder.
+    Lzip(Box<Decoder<SniffedInput<R>>>),
+    /// The input is passed through unchanged.
+    Plain(SniffedInput<R>),
+}
+
+impl<R: Read> MaybeDecoder<R> {
+    /// Creates a new `MaybeDecoder`, sniffing the start of `input`.
+    pub fn new(input: R) -> Result<Self, LzipError> {
+        Self::new_with_options(input, DecodeOptions::default())
+    }
+
+    /// Creates a new `MaybeDecoder`, decompressing lzip data with the given options.
+    pub fn new_with_options(mut input: R, options: DecodeOptions) -> Result<Self, LzipError> {
+        let mut prefix = Vec::with_capacity(LZIP_MAGIC.len());
+        input
+            .by_ref()
+            .take(LZIP_MAGIC.len() as u64)
+            .read_to_end(&mut prefix)?;
+
+        let is_lzip = prefix == LZIP_MAGIC;
+        let input = io::Cursor::new(prefix).chain(input);
+        Ok(if is_lzip {
+            MaybeDecoder::Lzip(Box::new(Decoder::new_with_options(input, options)))
+        } else {
+            MaybeDecoder::Plain(input)
+        })
+    }
+
+    /// Returns whether the input is lzip data being decompressed.
+    pub fn is_lzip(&self) -> bool {
+        matches!(self, MaybeDecoder::Lzip(_))
+    }
+}
+
+impl<R: Read> Read for MaybeDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            MaybeDecoder::Lzip(decoder) => decoder.read(buf),
+            MaybeDecoder::Plain(input) => input.read(buf),
+        }
+    }
+}
+
+/// Returns whether a read failing with `err` may succeed when retried.
+fn is_transient(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::Interrupted
+            | io::ErrorKind::WouldBlock
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
+
+/// Decodes the dictionary size from the single byte stored in the lzip header.
+///
+/// The low 5 bits hold the base 2 logarithm of the size, from which the high 3 bits subtract
+/// sixteenths. The exponent is checked before the size is computed, so out of range bytes are
+/// rejected without shifting by them.
+///
+/// Returns [`LzipError::InvalidDictSize`] if the decoded size is not between 4 KiB and 512 MiB.
+pub fn decode_dict_size(dict_size: u8) -> Result<u32, LzipError> {
+    let exponent = (dict_size & 0x1F) as u32;
+    if !(MIN_DICT_SIZE.ilog2()..=MAX_DICT_SIZE.ilog2()).contains(&exponent) {
+        return Err(LzipError::InvalidDictSize);
+    }
+
+    // Subtracting at most 7/16 from a size above 4 KiB can't drop it below 4 KiB.
+    let mut ds: u32 = 1 << exponent;
+    if ds > MIN_DICT_SIZE {
+        ds -= (ds / 16) * (((dict_size as u32) >> 5) & 0x07);
+    }
+
+    Ok(ds)
+}
+
+/// Returns the estimated memory in bytes needed to decode a member declaring `dict_size`.
+///
+/// This is the memory liblzma reports for the LZMA decoder, which is dominated by the
+/// dictionary. The buffers of the [`Decoder`] itself aren't included. Returns
+/// [`LzipError::InvalidDictSize`] if the size is not between 4 KiB and 512 MiB.
+pub fn decoder_memory_usage(dict_size: u32) -> Result<u64, LzipError> {
+    if !(MIN_DICT_SIZE..=MAX_DICT_SIZE).contains(&dict_size) {
+        return Err(LzipError::InvalidDictSize);
+    }
+
+    decoder_memusage(dict_size)
+}
+
+impl<R: Read> Read for Decoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.filled {
+            let n = (self.filled - self.pos).min(buf.len());
+            buf[..n].copy_from_slice(&self.buffer[self.pos..self.pos + n]);
+            self.pos += n;
+            return Ok(n);
+        }
+
+        Ok(self.read_decoded(buf)?)
+    }
+}
+
+impl<R: Read> BufRead for Decoder<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos >= self.filled {
+            let mut buffer = mem::take(&mut self.buffer);
+            if buffer.capacity() == 0 {
+                buffer = self.options.take_buffer();
+            }
+            buffer.resize(4096, 0);
+
+            let result = self.read_decoded(&mut buffer);
+            self.buffer = buffer;
+
+            self.filled = result?;
+            self.pos = 0;
+        }
+
+        Ok(&self.buffer[self.pos..self.filled])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.filled);
+    }
+}
+
+impl<R: Read> Drop for Decoder<R> {
+    fn drop(&mut self) {
+        if let Some(pool) = &self.options.buffer_pool {
+            pool.give(mem::take(&mut self.buffer));
+            pool.give(mem::take(&mut self.held));
+            pool.give(mem::take(&mut self.ready));
+        }
     }
 }
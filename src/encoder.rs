@@ -3,17 +3,43 @@
 
 //! Handles the compression of lzip data.
 
-use std::io::{BufRead, BufReader, Read, Write};
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read, Seek, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Receiver;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use crc32fast::Hasher;
-use liblzma::stream::{Action, Filters, LzmaOptions, Stream};
+use liblzma::stream::{self, Action, Status};
 
-use crate::LzipError;
-use crate::{LZIP_MAGIC, LZIP_VERSION, MIN_DICT_SIZE};
+use crate::budget::{MemoryBudget, Reservation};
+use crate::format::{Header, Trailer, member_size};
+use crate::index::{IndexEntry, encode_index};
+use crate::lzma1::{self, LzmaParams, new_raw_encoder};
+use crate::pool::BufferPool;
+use crate::{LzipError, decode_dict_size, processed};
+use crate::{MAX_DICT_SIZE, MIN_DICT_SIZE};
+
+/// The size of the buffer a member is compressed through.
+const OUTPUT_BUFFER_SIZE: usize = 4096;
+
+/// The size an adaptive output buffer grows up to, unless set through
+/// [`EncoderOptions::adaptive_buffer_cap`].
+const DEFAULT_ADAPTIVE_BUFFER_CAP: usize = 64 * 1024;
+
+/// The number of chunks in a row which have to fill an adaptive output buffer to grow it.
+const GROW_AFTER_FULL_CHUNKS: u32 = 4;
+
+/// The size of the sample compressed to decide whether
+/// [`EncoderOptions::store_if_incompressible`] falls back to the fastest level.
+const COMPRESSIBILITY_SAMPLE_SIZE: usize = 64 * 1024;
 
 /// An enum representing the compression level for lzip.
 /// The compression level can be set to `Fastest`, `Fast`, `Default`, or `Maximum`.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
 pub enum CompressionLevel {
     /// Fastest compression level.
     Fastest = 0,
@@ -25,29 +51,571 @@ pub enum CompressionLevel {
     Maximum = 9,
 }
 
-/// A struct for compressing data using the lzip format.
+impl CompressionLevel {
+    /// Reads a compression level from the environment variable `name`, e.g. `LZIPPER_LEVEL`.
+    ///
+    /// The accepted values are the numbers `0`, `3`, `6` and `9`, or the names `fastest`,
+    /// `fast`, `default` and `maximum` (also `max`), in lowercase. Returns `None` if the variable
+    /// is unset or holds anything else, including surrounding whitespace.
+    ///
+    /// The environment is only read when this is called; [`EncoderOptions::default`] always
+    /// uses [`CompressionLevel::Default`], so a level from the environment has to be passed to
+    /// [`EncoderOptions::level`] explicitly.
+    pub fn from_env(name: &str) -> Option<Self> {
+        let value = env::var(name).ok()?;
+
+        match value.as_str() {
+            "0" | "fastest" => Some(CompressionLevel::Fastest),
+            "3" | "fast" => Some(CompressionLevel::Fast),
+            "6" | "default" => Some(CompressionLevel::Default),
+            "9" | "maximum" | "max" => Some(CompressionLevel::Maximum),
+            _ => None,
+        }
+    }
+}
+
+/// Options controlling how an [`Encoder`] compresses data.
 ///
 /// # Example
 ///
 /// ```no_run
-/// use lzipper::Encoder;
+/// use lzipper::{CompressionLevel, Encoder, EncoderOptions};
 ///
 /// let input = b"the quick brown fox jumps over the lazy dog";
+/// let options = EncoderOptions::default()
+///     .level(CompressionLevel::Maximum)
+///     .dict_size(1 << 20);
 /// let mut encoded: Vec<u8> = Vec::new();
-/// let mut encoder = Encoder::new(input.as_slice());
+/// let mut encoder = Encoder::new_with_options(input.as_slice(), options);
 /// encoder.encode(&mut encoded).expect("failed to encode");
 /// ```
+#[derive(Clone, Debug)]
+pub struct EncoderOptions {
+    /// The compression level.
+    level: CompressionLevel,
+    /// The dictionary size overriding the one implied by the compression level.
+    dict_size: Option<u32>,
+    /// The token which cancels compression once set.
+    cancel_token: Option<Arc<AtomicBool>>,
+    /// The number of uncompressed bytes per member when splitting the input into members.
+    block_size: Option<u64>,
+    /// The number of threads compressing members in parallel.
+    threads: usize,
+    /// Whether to shrink the dictionary to fit the input size.
+    auto_dict: bool,
+    /// Whether to fall back to the fastest level for input that hardly compresses.
+    store_if_incompressible: bool,
+    /// Whether to fail if a member ends up larger than its uncompressed data.
+    error_on_expansion: bool,
+    /// Whether to grow the output buffer while the compressed data keeps filling it.
+    adaptive_buffer: bool,
+    /// The size the adaptive output buffer grows up to.
+    adaptive_buffer_cap: usize,
+    /// The number of compressed bytes written between flushes of the output, if flushing.
+    flush_interval: Option<usize>,
+    /// Whether to append an index of the members after the last one.
+    append_index: bool,
+    /// The nonstandard LZMA parameters overriding lzip's lc=3, lp=0 and pb=2.
+    lzma_params: Option<LzmaParams>,
+    /// The liblzma preset overriding the one of the compression level.
+    preset: Option<u32>,
+    /// The size of the input, if known.
+    input_size: Option<u64>,
+    /// The number of bytes to read ahead to size the dictionary of an input of unknown size.
+    probe_size: Option<usize>,
+    /// The start and length of the time budget set by [`Encoder::encode_adaptive`].
+    budget: Option<(Instant, Duration)>,
+    /// The pool to borrow working buffers from.
+    buffer_pool: Option<BufferPool>,
+    /// The budget the memory of each member is reserved from.
+    memory_budget: Option<MemoryBudget>,
+}
+
+impl Default for EncoderOptions {
+    fn default() -> Self {
+        EncoderOptions {
+            level: CompressionLevel::Default,
+            dict_size: None,
+            cancel_token: None,
+            block_size: None,
+            threads: 1,
+            auto_dict: false,
+            store_if_incompressible: false,
+            error_on_expansion: false,
+            adaptive_buffer: false,
+            adaptive_buffer_cap: DEFAULT_ADAPTIVE_BUFFER_CAP,
+            flush_interval: None,
+            append_index: false,
+            lzma_params: None,
+            preset: None,
+            input_size: None,
+            probe_size: None,
+            budget: None,
+            buffer_pool: None,
+            memory_budget: None,
+        }
+    }
+}
+
+/// The concrete settings an [`Encoder`] compresses with, as returned by
+/// [`EncoderOptions::resolve`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResolvedOptions {
+    /// The compression level.
+    pub level: CompressionLevel,
+    /// The liblzma preset selecting the match finder, the one of the level unless set through
+    /// [`Encoder::new_with_preset_and_dict`].
+    pub preset: u32,
+    /// The dictionary size, after the override and [`EncoderOptions::auto_dict`] are applied.
+    pub dict_size: u32,
+    /// The number of literal context bits.
+    pub lc: u32,
+    /// The number of literal position bits.
+    pub lp: u32,
+    /// The number of position bits.
+    pub pb: u32,
+    /// The length of a match considered good enough to stop searching for a longer one.
+    pub nice_len: u32,
+    /// The number of uncompressed bytes per member, or `None` if the input is compressed into
+    /// a single member.
+    pub block_size: Option<u64>,
+    /// The number of threads compressing members in parallel.
+    pub threads: usize,
+}
+
+impl ResolvedOptions {
+    /// Returns the literal context, literal position and position bits.
+    pub(crate) fn lzma_params(&self) -> LzmaParams {
+        LzmaParams {
+            lc: self.lc,
+            lp: self.lp,
+            pb: self.pb,
+        }
+    }
+}
+
+impl EncoderOptions {
+    /// Sets the compression level. Defaults to [`CompressionLevel::Default`].
+    pub fn level(mut self, level: CompressionLevel) -> Self {
+        self.level = level;
+        self
+    }
+
+    /// Sets the dictionary size, overriding the one implied by the compression level.
+    ///
+    /// The dictionary size must be between 4 KiB and 512 MiB, otherwise encoding fails with
+    /// [`LzipError::InvalidDictSize`].
+    pub fn dict_size(mut self, dict_size: u32) -> Self {
+        self.dict_size = Some(dict_size);
+        self
+    }
+
+    /// Sets a token which cancels compression once set to `true`.
+    ///
+    /// The token is checked before every chunk of input is compressed. Once it is set, encoding
+    /// fails with [`LzipError::Cancelled`] and the output is left with an incomplete member.
+    pub fn cancel_token(mut self, token: Arc<AtomicBool>) -> Self {
+        self.cancel_token = Some(token);
+        self
+    }
+
+    /// Splits the input into members of `block_size` uncompressed bytes each.
+    ///
+    /// Every member is compressed independently, which is how lzip achieves parallelism: unlike
+    /// xz, the lzip format has no blocks within a member, so the work is divided into separate
+    /// members instead. Splitting slightly lowers the compression ratio since matches can't
+    /// reach across members. Defaults to a single member for the whole input, or to twice the
+    /// dictionary size if [`EncoderOptions::threads`] is greater than one.
+    ///
+    /// Members are also the only points decoding can start from. The raw LZMA1 stream inside a
+    /// member can't be flushed or reset midway, so for random access the input has to be split
+    /// into members, best with [`EncoderOptions::append_index`] to find them through
+    /// [`Decoder::read_index`](crate::Decoder::read_index).
+    pub fn block_size(mut self, block_size: u64) -> Self {
+        self.block_size = Some(block_size.max(1));
+        self
+    }
+
+    /// Sets the number of threads compressing members in parallel. Defaults to 1.
+    ///
+    /// With more than one thread the input is split into members as described in
+    /// [`EncoderOptions::block_size`]. Up to `threads` blocks are held in memory at once, along
+    /// with their compressed output.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets whether to shrink the dictionary to fit the input. Defaults to `false`.
+    ///
+    /// When enabled and the input size is known (see [`EncoderOptions::input_size`]), the
+    /// dictionary is sized to the smallest size the lzip header can declare which holds the
+    /// whole input, but never below 4 KiB or above the dictionary size that would otherwise be
+    /// used. Besides powers of two, the header declares sizes one to seven sixteenths below
+    /// them, so e.g. 5000 bytes of input get a 5 KiB dictionary rather than 8 KiB. A smaller
+    /// dictionary lowers the memory needed to encode and decode the data without affecting the
+    /// compression ratio.
+    pub fn auto_dict(mut self, auto_dict: bool) -> Self {
+        self.auto_dict = auto_dict;
+        self
+    }
+
+    /// Sets whether to fall back to the fastest level for input that hardly compresses, such as
+    /// already compressed images. Defaults to `false`.
+    ///
+    /// When enabled, the first 64 KiB of each member's input are compressed as a sample, and if
+    /// they shrink by less than a tenth, that member is compressed with
+    /// [`CompressionLevel::Fastest`] (and its dictionary size, unless one was set). The decision
+    /// is made again for every member, so later input that compresses well still gets the
+    /// configured level. lzip has no
+    /// stored blocks, so the output is still LZMA data; this only saves the CPU time the higher
+    /// level would spend for next to no gain.
+    pub fn store_if_incompressible(mut self, store_if_incompressible: bool) -> Self {
+        self.store_if_incompressible = store_if_incompressible;
+        self
+    }
+
+    /// Sets whether to fail with [`LzipError::Expanded`] if a member, including its header and
+    /// trailer, ends up larger than the data compressed into it. Defaults to `false`.
+    ///
+    /// lzip can't store data uncompressed, so tiny or incompressible inputs expand. This lets a
+    /// caller with its own container store such data raw instead. The check is made once the
+    /// member is complete, so with [`Encoder::encode`] the expanded member has already been
+    /// written to the output and has to be discarded.
+    pub fn error_on_expansion(mut self, error_on_expansion: bool) -> Self {
+        self.error_on_expansion = error_on_expansion;
+        self
+    }
+
+    /// Sets whether to grow the output buffer while the compressed data keeps filling it.
+    /// Defaults to `false`.
+    ///
+    /// A member is normally compressed through a fixed 4 KiB buffer on the stack. When enabled,
+    /// the buffer moves to the heap and doubles in size whenever a few chunks in a row fill it,
+    /// up to [`EncoderOptions::adaptive_buffer_cap`]. Data that compresses poorly then moves
+    /// through fewer, larger writes, while well compressed data, which rarely fills the buffer,
+    /// keeps it small.
+    pub fn adaptive_buffer(mut self, adaptive_buffer: bool) -> Self {
+        self.adaptive_buffer = adaptive_buffer;
+        self
+    }
+
+    /// Sets the size the output buffer grows up to with [`EncoderOptions::adaptive_buffer`].
+    /// Defaults to 64 KiB, and is never less than 4 KiB.
+    pub fn adaptive_buffer_cap(mut self, cap: usize) -> Self {
+        self.adaptive_buffer_cap = cap.max(OUTPUT_BUFFER_SIZE);
+        self
+    }
+
+    /// Sets the number of compressed bytes to write between flushes of the output. Defaults to
+    /// never flushing, and `0` turns flushing off again.
+    ///
+    /// The output is flushed as soon as at least `interval` bytes of compressed data have been
+    /// written to it since the last flush, so a sink like a chunked HTTP response delivers them
+    /// promptly instead of buffering the whole member. A failed flush fails the encode with
+    /// [`LzipError::IoError`]. Only a member compressed straight to the output by
+    /// [`Encoder::encode`] is flushed; members compressed in blocks are written whole.
+    pub fn flush_interval(mut self, interval: usize) -> Self {
+        self.flush_interval = (interval > 0).then_some(interval);
+        self
+    }
+
+    /// Sets whether to append an index of the members after the last one. Defaults to `false`.
+    ///
+    /// The index records the offset and size of every member written by [`Encoder::encode`]
+    /// or [`Encoder::encode_append`], so [`Decoder::read_index`](crate::Decoder::read_index)
+    /// can find the members without walking their trailers. It is a lzipper extension, not
+    /// part of the lzip format: lzip ignores it as trailing data by default and rejects it with
+    /// `--trailing-error`. See the [`index`](crate::index) module for its layout.
+    pub fn append_index(mut self, append_index: bool) -> Self {
+        self.append_index = append_index;
+        self
+    }
+
+    /// Sets nonstandard literal context (`lc`), literal position (`lp`) and position (`pb`)
+    /// bits for the LZMA data. Defaults to lzip's fixed lc=3, lp=0 and pb=2.
+    ///
+    /// lzip doesn't store these parameters, so the output is not a standard lzip file: `lzip`
+    /// and other decoders fail on it, and it only decodes with
+    /// [`DecodeOptions::lzma_params`](crate::DecodeOptions::lzma_params) set to the same
+    /// values. Tuning them can help data with a fixed record structure, e.g. `lp=2, pb=2` for
+    /// 32-bit words. Invalid values, such as `lc + lp` above 4 or `pb` above 4, fail when
+    /// compression starts.
+    pub fn lzma_params(mut self, lc: u32, lp: u32, pb: u32) -> Self {
+        self.lzma_params = Some(LzmaParams { lc, lp, pb });
+        self
+    }
+
+    /// Declares the size of the input in bytes, if it is known up front.
+    pub fn input_size(mut self, input_size: u64) -> Self {
+        self.input_size = Some(input_size);
+        self
+    }
+
+    /// Sets the number of bytes to read ahead to size the dictionary for an input of unknown
+    /// size, e.g. piped from stdin. Defaults to no probing, and `0` turns it off again.
+    ///
+    /// Unless [`EncoderOptions::input_size`] is set, up to `probe_size` bytes are buffered
+    /// before compression starts. If the input ends within them, its size is known and the
+    /// dictionary is shrunk to fit it like [`EncoderOptions::auto_dict`] does; otherwise the
+    /// dictionary of the level (or the override) is used. Either way, the buffered bytes are
+    /// compressed first and the rest of the input is streamed as usual.
+    pub fn probe_size(mut self, probe_size: usize) -> Self {
+        self.probe_size = (probe_size > 0).then_some(probe_size);
+        self
+    }
+
+    /// Sets a pool to borrow the encoder's working buffers from.
+    ///
+    /// When the input is split into members (see [`EncoderOptions::block_size`] and
+    /// [`EncoderOptions::threads`]), the buffers holding each block and its compressed member
+    /// are taken from the pool and given back once the member is written. A single member is
+    /// compressed through a buffer on the stack and needs none.
+    pub fn buffer_pool(mut self, pool: BufferPool) -> Self {
+        self.buffer_pool = Some(pool);
+        self
+    }
+
+    /// Sets a budget to reserve the memory of compressing each member from.
+    ///
+    /// Before a member's LZMA stream is created, the memory it needs is estimated like
+    /// [`encoder_memory_usage`] does for a single thread, plus the input and output buffers,
+    /// and reserved from the budget until the member is written. If that would exceed the
+    /// budget, compression fails with [`LzipError::MemoryLimitExceeded`] without allocating it.
+    /// With [`EncoderOptions::threads`], every thread reserves for its own member, but the
+    /// blocks and members buffered between them aren't counted. [`compress_in_place`] doesn't
+    /// take options and isn't covered.
+    pub fn memory_budget(mut self, budget: MemoryBudget) -> Self {
+        self.memory_budget = Some(budget);
+        self
+    }
+
+    /// Computes the concrete settings compression will use.
+    ///
+    /// The dictionary size is the override or the one implied by the level, shrunk to fit the
+    /// input if [`EncoderOptions::auto_dict`] is set. The LZMA parameters are lzip's lc=3,
+    /// lp=0 and pb=2 unless overridden, and the nice length is the one of the level's preset.
+    /// The block size is only set if the input is split into members, and defaults to twice
+    /// the dictionary size.
+    ///
+    /// Returns [`LzipError::InvalidDictSize`] if the dictionary size is not between 4 KiB and
+    /// 512 MiB, and a [`LzipError::StreamError`] if the LZMA parameters are invalid.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use lzipper::{CompressionLevel, EncoderOptions};
+    ///
+    /// let options = EncoderOptions::default().level(CompressionLevel::Maximum);
+    /// let resolved = options.resolve().expect("invalid options");
+    /// assert_eq!(resolved.dict_size, 1 << 26);
+    /// assert_eq!((resolved.lc, resolved.lp, resolved.pb), (3, 0, 2));
+    /// ```
+    pub fn resolve(&self) -> Result<ResolvedOptions, LzipError> {
+        let dict_size = self.effective_dict_size();
+        encode_dict_size(dict_size)?;
+
+        let LzmaParams { lc, lp, pb } = self.lzma_params.unwrap_or(LzmaParams {
+            lc: 3,
+            lp: 0,
+            pb: 2,
+        });
+        if lc + lp > 4 || pb > 4 {
+            return Err(LzipError::StreamError(stream::Error::Options));
+        }
+
+        let preset = self.preset.unwrap_or(self.level as u32);
+        if preset > 9 {
+            return Err(LzipError::StreamError(stream::Error::Options));
+        }
+
+        let block_size = (self.block_size.is_some() || self.threads > 1)
+            .then(|| self.block_size.unwrap_or(2 * dict_size as u64));
+
+        Ok(ResolvedOptions {
+            level: self.level,
+            preset,
+            dict_size,
+            lc,
+            lp,
+            pb,
+            nice_len: preset_nice_len(preset),
+            block_size,
+            threads: self.threads,
+        })
+    }
+
+    /// Returns the dictionary size, either the override or the one implied by the level, shrunk
+    /// to fit the input if requested.
+    pub(crate) fn effective_dict_size(&self) -> u32 {
+        let dict_size = self
+            .dict_size
+            .unwrap_or_else(|| level_dict_size(self.level));
+
+        match self.input_size {
+            Some(input_size) if self.auto_dict => dict_size.min(min_dict_size_for(input_size)),
+            _ => dict_size,
+        }
+    }
+
+    /// Takes a buffer from the pool, or returns a new empty buffer without a pool.
+    fn take_buffer(&self) -> Vec<u8> {
+        self.buffer_pool
+            .as_ref()
+            .map_or_else(Vec::new, BufferPool::take)
+    }
+
+    /// Gives a buffer back to the pool, if there is one.
+    fn give_buffer(&self, buffer: Vec<u8>) {
+        if let Some(pool) = &self.buffer_pool {
+            pool.give(buffer);
+        }
+    }
+
+    /// Returns whether compression has been cancelled.
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel_token
+            .as_ref()
+            .is_some_and(|token| token.load(Ordering::Relaxed))
+    }
+
+    /// Returns whether the time budget is exceeded, or projected to be exceeded.
+    ///
+    /// `member_start` is when compression of the current member started and `consumed` the
+    /// number of its input bytes compressed so far. The projection is only made when the size
+    /// of the member's input is known.
+    fn over_budget(&self, member_start: Instant, consumed: u64) -> bool {
+        let Some((start, budget)) = self.budget else {
+            return false;
+        };
+
+        let elapsed = start.elapsed();
+        if elapsed >= budget {
+            return true;
+        }
+
+        let Some(size) = self.input_size.filter(|_| consumed > 0) else {
+            return false;
+        };
+        let member_elapsed = member_start.elapsed().as_secs_f64();
+        let projected =
+            elapsed.as_secs_f64() - member_elapsed + member_elapsed * size as f64 / consumed as f64;
+
+        projected > budget.as_secs_f64()
+    }
+}
+
+/// Statistics collected while encoding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EncodeStats {
+    /// The number of members written.
+    pub members: u64,
+    /// The size of the uncompressed data.
+    pub uncompressed_size: u64,
+    /// The size of the compressed output, including the header and trailer of every member
+    /// and any index.
+    pub compressed_size: u64,
+}
+
+/// A writer computing the CRC32 of everything written through it.
+struct HashingWriter<'a, W> {
+    /// The writer the data goes to.
+    inner: &'a mut W,
+    /// The CRC32 of the data written so far.
+    hasher: Hasher,
+}
+
+impl<W: Write> Write for HashingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader which may know how many bytes are left to read from it.
+///
+/// Used by [`Encoder::new_sized`] to fill in [`EncoderOptions::input_size`].
+pub trait KnownLength {
+    /// Returns the number of bytes left to read, or `None` if it is unknown.
+    fn len(&self) -> Option<u64>;
+
+    /// Returns whether there are no bytes left to read, or `None` if it is unknown.
+    fn is_empty(&self) -> Option<bool> {
+        self.len().map(|len| len == 0)
+    }
+}
+
+impl KnownLength for &[u8] {
+    fn len(&self) -> Option<u64> {
+        Some(<[u8]>::len(self) as u64)
+    }
+}
+
+impl KnownLength for Cursor<Vec<u8>> {
+    fn len(&self) -> Option<u64> {
+        Some((self.get_ref().len() as u64).saturating_sub(self.position()))
+    }
+}
+
+impl KnownLength for File {
+    fn len(&self) -> Option<u64> {
+        let mut file = self;
+        let position = file.stream_position().ok()?;
+        let len = self.metadata().ok()?.len();
+
+        Some(len.saturating_sub(position))
+    }
+}
+
+/// A struct for compressing data using the lzip format.
+///
+/// An `Encoder` is [`Send`] and [`Sync`] whenever `R` is, so it can be moved into another
+/// thread together with its input. No LZMA stream is kept between calls, each one is created
+/// on the thread doing the compression. Note that [`std::thread::spawn`] also requires the
+/// input to be `'static`, so a borrowed slice has to be replaced by an owned buffer, e.g. a
+/// `Cursor<Vec<u8>>`, or the thread spawned with [`std::thread::scope`].
+///
+/// # Reproducibility
+///
+/// The output is byte-identical across runs and machines for the same input and options, as
+/// long as the same liblzma version is used: the lzip header and trailer are fully determined
+/// by the data, and liblzma's LZMA1 encoder is deterministic however the input is split into
+/// reads. This also holds with [`EncoderOptions::threads`], as the input is split into members
+/// at fixed offsets and they are written in order; for a given [`EncoderOptions::block_size`],
+/// the output is the same with any number of threads.
+///
+/// # Example
+///
+/// ```no_run
+/// use lzipper::Encoder;
+///
+/// let input = b"the quick brown fox jumps over the lazy dog";
+/// let encoded = Encoder::new(input.as_slice())
+///     .finish_to_vec()
+///     .expect("failed to encode");
+/// ```
 pub struct Encoder<R: Read> {
     /// The input data stream.
-    input: BufReader<R>,
-    /// The compression level.
-    compression_level: CompressionLevel,
+    input: BufReader<ProbedReader<R>>,
+    /// The options used for compression.
+    options: EncoderOptions,
     /// The CRC32 of the uncompressed data.
     crc32: u32,
     // The size of the uncompressed data.
     uncompressed_size: u64,
     // The size of the compressed data.
     compressed_size: u64,
+    /// The member bytes written to the output by the last encode.
+    written: u64,
+    /// The sum of the member sizes declared in the trailers written by the last encode.
+    declared: u64,
+    /// The statistics of the last encode.
+    stats: EncodeStats,
 }
 
 impl<R: Read> Encoder<R> {
@@ -63,122 +631,901 @@ impl<R: Read> Encoder<R> {
     /// The `input` parameter is a stream of data to be compressed.
     /// The `level` parameter specifies the compression level.
     pub fn new_with_level(input: R, level: CompressionLevel) -> Self {
+        Self::new_with_options(input, EncoderOptions::default().level(level))
+    }
+
+    /// Creates a new `Encoder` instance.
+    ///
+    /// The `input` parameter is a stream of data to be compressed.
+    /// The `options` parameter controls how the data is compressed.
+    pub fn new_with_options(input: R, options: EncoderOptions) -> Self {
         Encoder {
-            input: BufReader::new(input),
-            compression_level: level,
+            input: BufReader::new(ProbedReader {
+                probe: Vec::new(),
+                pos: 0,
+                probed: false,
+                inner: input,
+            }),
+            options,
             crc32: 0,
             uncompressed_size: 0,
             compressed_size: 0,
+            written: 0,
+            declared: 0,
+            stats: EncodeStats::default(),
+        }
+    }
+
+    /// Creates a new `Encoder` instance compressing with the match finder of the liblzma
+    /// `preset` and a dictionary of `dict_size` bytes.
+    ///
+    /// The presets `0` to `9` are those of `lzip -0` to `lzip -9`, of which
+    /// [`CompressionLevel`] only names every third, so e.g. preset 9's thorough match finder can
+    /// be combined with a 1 MiB dictionary. The other options are the defaults.
+    ///
+    /// Returns a [`LzipError::StreamError`] if the preset is above 9, and
+    /// [`LzipError::InvalidDictSize`] if the dictionary size is not between 4 KiB and 512 MiB.
+    pub fn new_with_preset_and_dict(
+        input: R,
+        preset: u32,
+        dict_size: u32,
+    ) -> Result<Self, LzipError> {
+        let options = EncoderOptions {
+            preset: Some(preset),
+            ..EncoderOptions::default().dict_size(dict_size)
+        };
+        options.resolve()?;
+
+        Ok(Self::new_with_options(input, options))
+    }
+
+    /// Creates a new `Encoder` instance for an input of known length.
+    ///
+    /// Unless already set, [`EncoderOptions::input_size`] is filled in from the length reported
+    /// by `input`, which lets options such as [`EncoderOptions::auto_dict`] take effect.
+    pub fn new_sized(input: R, mut options: EncoderOptions) -> Self
+    where
+        R: KnownLength,
+    {
+        if options.input_size.is_none() {
+            options.input_size = input.len();
         }
+
+        Self::new_with_options(input, options)
     }
 
     /// Compresses the data from the input stream and writes it to the output stream.
     ///
     /// The `output` parameter is a writable stream where the compressed data will be written.
     pub fn encode<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
-        self.write_header(output)?;
-        self.compress(output)?;
+        self.probe_input()?;
+        self.begin_encode();
+
+        if let Some(block_size) = self.options.resolve()?.block_size {
+            let entries = self.encode_blocks(output, block_size)?;
+            debug_assert!(self.self_check());
+            return self.write_index(output, &entries);
+        }
+
+        let resolved = self.resolve_member()?;
+        self.write_header(output, resolved.dict_size)?;
+        self.compress(output, &resolved)?;
+        self.written += self.compressed_size;
         self.write_trailer(output)?;
+        debug_assert!(self.self_check());
+        self.check_expansion()?;
+
+        self.write_index(output, &[self.member_entry()])
+    }
+
+    /// Compresses the data from the input stream to the output stream like [`Encoder::encode`],
+    /// computing a CRC32 of the compressed output on the way.
+    ///
+    /// lzip's own CRC32 only covers the uncompressed data of each member. This one covers every
+    /// byte written to `output`, headers, trailers and any [index](crate::index) included, so
+    /// it can be stored e.g. in an external index to check the integrity of the compressed file
+    /// itself without decompressing it. Returns the CRC32 along with the statistics of the
+    /// encode.
+    pub fn encode_with_output_crc<W: Write>(
+        &mut self,
+        output: &mut W,
+    ) -> Result<(u32, EncodeStats), LzipError> {
+        let mut output = HashingWriter {
+            inner: output,
+            hasher: Hasher::new(),
+        };
+        self.encode(&mut output)?;
+
+        Ok((output.hasher.finalize(), self.stats.clone()))
+    }
+
+    /// Returns whether the members written by the last encode are exactly as large as their
+    /// trailers declare.
+    ///
+    /// Every member written by [`Encoder::encode`], [`Encoder::encode_append`] or
+    /// [`Encoder::checkpoint`] is counted as it goes to the output, and the count is compared
+    /// with the member sizes stored in the trailers, i.e. the header, compressed data and
+    /// trailer of each member must add up to its `member_size`. A mismatch would produce a file
+    /// failing its own trailer check with [`LzipError::InvalidMemberSize`] on decode. This is
+    /// checked by a debug assertion after every encode too. Returns `true` before anything has
+    /// been encoded.
+    pub fn self_check(&self) -> bool {
+        self.written == self.declared
+    }
+
+    /// Compresses the data available from the input stream into one complete member and
+    /// flushes the output.
+    ///
+    /// The member ends where the input currently ends, and the next call starts a new member
+    /// with whatever the input yields after that, e.g. the data appended to a log file since.
+    /// Once this returns, the output holds only complete members, so the caller can sync it to
+    /// disk knowing a crash never leaves a half-written member behind. Nothing is written if
+    /// the input has no new data. The input is always compressed into a single member,
+    /// regardless of [`EncoderOptions::block_size`] and [`EncoderOptions::threads`], while
+    /// [`EncoderOptions::store_if_incompressible`] and [`EncoderOptions::error_on_expansion`]
+    /// apply to each member like they do for [`Encoder::encode`]. An expanded member has been
+    /// written and flushed when [`LzipError::Expanded`] is returned.
+    pub fn checkpoint<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
+        self.begin_encode();
+        if self.input.fill_buf()?.is_empty() {
+            return Ok(());
+        }
+
+        let resolved = self.resolve_member()?;
+        self.write_header(output, resolved.dict_size)?;
+        self.compress(output, &resolved)?;
+        self.written += self.compressed_size;
+        self.write_trailer(output)?;
+        debug_assert!(self.self_check());
+        output.flush()?;
+
+        self.check_expansion()
+    }
+
+    /// Compresses the data from the input stream to the output stream within a time budget.
+    ///
+    /// The budget starts when this method is called. Before every chunk of input is compressed,
+    /// the elapsed time is checked and, if the input size is known (see
+    /// [`EncoderOptions::input_size`]), the total time is projected from the share of the input
+    /// compressed so far. Once either exceeds `budget`, compression stops with
+    /// [`LzipError::TimeBudgetExceeded`].
+    ///
+    /// The input is streamed rather than buffered, so compression is not retried at a lower
+    /// level; pick the level up front and use this to bound the time spent. When the budget is
+    /// exceeded, the output holds an incomplete member which must be discarded, and the input
+    /// has been partially consumed.
+    pub fn encode_adaptive<W: Write>(
+        &mut self,
+        output: &mut W,
+        budget: Duration,
+    ) -> Result<(), LzipError> {
+        self.options.budget = Some((Instant::now(), budget));
+        let result = self.encode(output);
+        self.options.budget = None;
+
+        result
+    }
+
+    /// Compresses the data from the input stream to the output stream as a bare LZMA1 payload,
+    /// without the lzip header and trailer.
+    ///
+    /// Returns the CRC32 and size of the uncompressed data, which together with the dictionary
+    /// size set through the options have to reach the receiver out of band to decode the payload with
+    /// [`Decoder::new_headerless`](crate::Decoder::new_headerless). The input is always
+    /// compressed into a single payload, regardless of [`EncoderOptions::block_size`] and
+    /// [`EncoderOptions::threads`]. The payload isn't a member, so it leaves what
+    /// [`Encoder::self_check`] compares untouched.
+    pub fn encode_headerless<W: Write>(&mut self, output: &mut W) -> Result<(u32, u64), LzipError> {
+        self.probe_input()?;
+        let resolved = self.options.resolve()?;
+        // A headerless payload has no member size to check, so it isn't counted as written.
+        self.compress(output, &resolved)?;
+
+        Ok((self.crc32, self.uncompressed_size))
+    }
+
+    /// Compresses all data from the input stream into a newly allocated `Vec`.
+    ///
+    /// This consumes the encoder and is equivalent to calling [`Encoder::encode_append`] with an
+    /// empty `Vec`, so the output is reserved up front when the input size is known.
+    pub fn finish_to_vec(mut self) -> Result<Vec<u8>, LzipError> {
+        let mut output = Vec::new();
+        self.encode_append(&mut output)?;
+
+        Ok(output)
+    }
+
+    /// Compresses all data from the input stream and appends it to the end of `out`.
+    ///
+    /// Unlike [`Encoder::encode`], the LZMA stream writes straight into the spare capacity of
+    /// `out` instead of going through an intermediate buffer and the `Write` trait. Calling this
+    /// repeatedly with different encoders builds a multi-member file in one buffer. On error,
+    /// `out` is truncated back to its original length.
+    ///
+    /// If the input size is known (see [`EncoderOptions::input_size`]), capacity for half of it
+    /// is reserved in `out` up front. That covers typical data without reallocating, and costs
+    /// at most one reallocation for incompressible data.
+    pub fn encode_append(&mut self, out: &mut Vec<u8>) -> Result<(), LzipError> {
+        self.probe_input()?;
+        if let Some(input_size) = self.options.input_size {
+            // Only a hint, the output still grows as needed if this fails.
+            let _ = out.try_reserve(reserve_hint(input_size));
+        }
+
+        self.begin_encode();
+
+        let start = out.len();
+        let result = if let Some(block_size) = self.options.resolve()?.block_size {
+            self.encode_blocks(out, block_size)
+                .and_then(|entries| self.write_index(out, &entries))
+        } else {
+            self.compress_append(out)
+                .and_then(|()| self.write_index(out, &[self.member_entry()]))
+        };
+
+        if result.is_err() {
+            out.truncate(start);
+        }
+        debug_assert!(result.is_err() || self.self_check());
+        result
+    }
+
+    /// Compresses a single member from the input stream to the end of `out`.
+    fn compress_append(&mut self, out: &mut Vec<u8>) -> Result<(), LzipError> {
+        let resolved = self.resolve_member()?;
+        let start = out.len();
+        out.extend_from_slice(&Header::new(resolved.dict_size).to_bytes()?);
+
+        let _reservation = self.reserve_memory(&resolved)?;
+        let mut stream = new_raw_encoder(&resolved)?;
+        let mut hasher = Hasher::new();
+        let member_start = Instant::now();
+
+        loop {
+            if self.options.is_cancelled() {
+                return Err(LzipError::Cancelled);
+            }
+            if self.options.over_budget(member_start, stream.total_in()) {
+                return Err(LzipError::TimeBudgetExceeded);
+            }
+
+            let input_buf = self.input.fill_buf()?;
+            let eof = input_buf.is_empty();
+
+            out.reserve(4096);
+            let before_len = out.len();
+            let before_in = stream.total_in();
+            stream.process_vec(
+                input_buf,
+                out,
+                if eof { Action::Finish } else { Action::Run },
+            )?;
+            let read = processed(before_in, stream.total_in(), input_buf.len())?;
+            let written = out.len() - before_len;
+
+            hasher.update(&input_buf[..read]);
+            self.input.consume(read);
+
+            if eof && written == 0 {
+                break;
+            }
+        }
+
+        self.crc32 = hasher.finalize();
+        self.uncompressed_size = stream.total_in();
+        self.compressed_size = stream.total_out();
+        let trailer = Trailer::new(self.crc32, self.uncompressed_size, self.compressed_size);
+        out.extend_from_slice(&trailer.to_bytes());
+        self.written += (out.len() - start) as u64;
+        self.record_member(trailer.member_size, self.uncompressed_size);
+
+        self.check_expansion()
+    }
+
+    /// Splits the input into blocks and compresses each block into its own member.
+    ///
+    /// Returns the location and size of each member written.
+    fn encode_blocks<W: Write>(
+        &mut self,
+        output: &mut W,
+        block_size: u64,
+    ) -> Result<Vec<IndexEntry>, LzipError> {
+        let threads = self.options.threads;
+        let member_options = EncoderOptions {
+            block_size: None,
+            threads: 1,
+            append_index: false,
+            ..self.options.clone()
+        };
+
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        let mut first = true;
+        loop {
+            let mut blocks = Vec::with_capacity(threads);
+            for _ in 0..threads {
+                let mut block = self.options.take_buffer();
+                self.input
+                    .by_ref()
+                    .take(block_size)
+                    .read_to_end(&mut block)?;
+
+                // An empty input still needs a single (empty) member.
+                if block.is_empty() && !(first && blocks.is_empty()) {
+                    self.options.give_buffer(block);
+                    break;
+                }
+                blocks.push(block);
+            }
+            first = false;
+
+            if blocks.is_empty() {
+                break;
+            }
+
+            let encode_block = |block: &[u8]| -> Result<Vec<u8>, LzipError> {
+                let options = member_options.clone().input_size(block.len() as u64);
+                let mut member = options.take_buffer();
+                Encoder::new_with_options(block, options).encode(&mut member)?;
+                Ok(member)
+            };
+
+            // A single block gains nothing from a thread of its own.
+            let members: Vec<Result<Vec<u8>, LzipError>> = if blocks.len() == 1 {
+                vec![encode_block(&blocks[0])]
+            } else {
+                thread::scope(|scope| {
+                    let handles: Vec<_> = blocks
+                        .iter()
+                        .map(|block| scope.spawn(|| encode_block(block)))
+                        .collect();
+
+                    handles
+                        .into_iter()
+                        .map(|handle| handle.join().expect("encoder thread panicked"))
+                        .collect()
+                })
+            };
+
+            for (member, block) in members.into_iter().zip(&blocks) {
+                let member = member?;
+                output.write_all(&member)?;
+                self.written += member.len() as u64;
+                if let Some(trailer) = member.last_chunk::<{ Trailer::SIZE }>() {
+                    self.record_member(
+                        Trailer::from_bytes(trailer).member_size,
+                        block.len() as u64,
+                    );
+                }
+                entries.push(IndexEntry {
+                    offset,
+                    member_size: member.len() as u64,
+                    uncompressed_size: block.len() as u64,
+                });
+                offset += member.len() as u64;
+                self.options.give_buffer(member);
+            }
+
+            let last = blocks.len() < threads;
+            for block in blocks {
+                self.options.give_buffer(block);
+            }
+            if last {
+                break;
+            }
+        }
+
+        Ok(entries)
+    }
+
+    /// Reads ahead up to [`EncoderOptions::probe_size`] bytes of an input of unknown size, and
+    /// sizes the dictionary to fit it if it ends within them.
+    fn probe_input(&mut self) -> Result<(), LzipError> {
+        let Some(probe_size) = self.options.probe_size else {
+            return Ok(());
+        };
+        if self.options.input_size.is_some() || self.input.get_ref().probed {
+            return Ok(());
+        }
+
+        // One byte past the probe tells whether the input ends within it.
+        let len = self.peek(probe_size.saturating_add(1))?.len();
+        if len <= probe_size {
+            self.options.input_size = Some(len as u64);
+            self.options.auto_dict = true;
+        }
+        self.input.get_mut().probed = true;
 
         Ok(())
     }
 
-    /// Write the lzip header to the output stream.
-    fn write_header<W: Write>(&self, output: &mut W) -> Result<(), LzipError> {
-        let mut header = [0; 6];
+    /// Returns the next `len` bytes of the input, or all of them if it ends first, without
+    /// consuming them.
+    ///
+    /// The input is read until `len` bytes are in, however it splits up its reads, so the
+    /// result only depends on the data.
+    fn peek(&mut self, len: usize) -> Result<&[u8], LzipError> {
+        // Data buffered already comes first, then whatever was read ahead before.
+        let mut ahead = self.input.buffer().to_vec();
+        self.input.consume(ahead.len());
+        let reader = self.input.get_mut();
+        ahead.extend_from_slice(&reader.probe[reader.pos..]);
+        if ahead.len() < len {
+            let limit = (len - ahead.len()) as u64;
+            (&mut reader.inner).take(limit).read_to_end(&mut ahead)?;
+        }
+
+        reader.probe = ahead;
+        reader.pos = 0;
+        Ok(&reader.probe[..len.min(reader.probe.len())])
+    }
+
+    /// Resets the sizes and statistics kept for the encode about to start.
+    fn begin_encode(&mut self) {
+        self.written = 0;
+        self.declared = 0;
+        self.stats = EncodeStats::default();
+    }
+
+    /// Records a member written with the given declared size and size of uncompressed data.
+    fn record_member(&mut self, member_size: u64, uncompressed_size: u64) {
+        self.declared += member_size;
+        self.stats.members += 1;
+        self.stats.uncompressed_size += uncompressed_size;
+        self.stats.compressed_size += member_size;
+    }
+
+    /// Returns the location and size of the single member just written.
+    fn member_entry(&self) -> IndexEntry {
+        IndexEntry {
+            offset: 0,
+            member_size: member_size(self.compressed_size),
+            uncompressed_size: self.uncompressed_size,
+        }
+    }
+
+    /// Writes the index of `entries` after the last member, if
+    /// [`EncoderOptions::append_index`] is enabled.
+    fn write_index<W: Write>(
+        &mut self,
+        output: &mut W,
+        entries: &[IndexEntry],
+    ) -> Result<(), LzipError> {
+        if self.options.append_index {
+            let index = encode_index(entries);
+            output.write_all(&index)?;
+            self.stats.compressed_size += index.len() as u64;
+        }
+
+        Ok(())
+    }
+
+    /// Resolves the options for the member about to be compressed, falling back to the fastest
+    /// level if a sample of its input hardly compresses, when
+    /// [`EncoderOptions::store_if_incompressible`] is enabled.
+    ///
+    /// The fallback only applies to this member; the options are left unchanged.
+    fn resolve_member(&mut self) -> Result<ResolvedOptions, LzipError> {
+        let resolved = self.options.resolve()?;
+        if !self.options.store_if_incompressible || resolved.level == CompressionLevel::Fastest {
+            return Ok(resolved);
+        }
+
+        let sample = self.peek(COMPRESSIBILITY_SAMPLE_SIZE)?;
+        if sample.is_empty() {
+            return Ok(resolved);
+        }
+
+        let options = EncoderOptions::default().level(CompressionLevel::Fastest);
+        let compressed = lzma1::encode(sample, &options)?;
+        if compressed.len() * 10 <= sample.len() * 9 {
+            return Ok(resolved);
+        }
 
-        header[0..4].copy_from_slice(&LZIP_MAGIC); // LZIP Magic
-        header[4] = LZIP_VERSION; // LZIP Version
-        header[5] = Self::encode_dict_size(self.dict_size()); // LZIP Encoded Dict Size
+        EncoderOptions {
+            level: CompressionLevel::Fastest,
+            preset: None,
+            ..self.options.clone()
+        }
+        .resolve()
+    }
 
-        output.write_all(&header)?;
+    /// Fails if the finished member is larger than its uncompressed data, when
+    /// [`EncoderOptions::error_on_expansion`] is enabled.
+    fn check_expansion(&self) -> Result<(), LzipError> {
+        if self.options.error_on_expansion
+            && member_size(self.compressed_size) > self.uncompressed_size
+        {
+            return Err(LzipError::Expanded);
+        }
 
         Ok(())
     }
 
-    /// Compress and write the data to the output stream.
-    fn compress<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
-        let options = LzmaOptions::new_preset(self.compression_level as u32)?;
-        let mut filters = Filters::new();
-        filters.lzma1(&options);
+    /// Write the lzip header declaring `dict_size` to the output stream.
+    fn write_header<W: Write>(&mut self, output: &mut W, dict_size: u32) -> Result<(), LzipError> {
+        output.write_all(&Header::new(dict_size).to_bytes()?)?;
+        self.written += Header::SIZE as u64;
+
+        Ok(())
+    }
 
-        let mut stream = Stream::new_raw_encoder(&filters)?;
+    /// Compress and write the data to the output stream with the `resolved` options.
+    ///
+    /// The compressed size is left in `compressed_size` for the caller to count as written.
+    fn compress<W: Write>(
+        &mut self,
+        output: &mut W,
+        resolved: &ResolvedOptions,
+    ) -> Result<(), LzipError> {
+        let _reservation = self.reserve_memory(resolved)?;
+        let mut stream = new_raw_encoder(resolved)?;
         let mut hasher = Hasher::new();
+        let member_start = Instant::now();
 
-        let mut output_buf = [0u8; 4096];
+        let mut stack_buf = [0u8; OUTPUT_BUFFER_SIZE];
+        let mut heap_buf: Vec<u8> = Vec::new();
+        let mut full_chunks = 0;
+        let mut bytes_written: u64 = 0;
+        let mut unflushed = 0;
 
         loop {
+            if self.options.is_cancelled() {
+                return Err(LzipError::Cancelled);
+            }
+            if self.options.over_budget(member_start, stream.total_in()) {
+                return Err(LzipError::TimeBudgetExceeded);
+            }
+
             let input_buf = self.input.fill_buf()?;
             let eof = input_buf.is_empty();
+            let output_buf: &mut [u8] = if heap_buf.is_empty() {
+                &mut stack_buf
+            } else {
+                &mut heap_buf
+            };
+            let capacity = output_buf.len();
 
             let before_out = stream.total_out();
             let before_in = stream.total_in();
             stream.process(
                 input_buf,
-                &mut output_buf,
+                output_buf,
                 if eof { Action::Finish } else { Action::Run },
             )?;
-            let read = (stream.total_in() - before_in) as usize;
-            let written = (stream.total_out() - before_out) as usize;
+            let read = processed(before_in, stream.total_in(), input_buf.len())?;
+            let written = processed(before_out, stream.total_out(), capacity)?;
 
+            // Hashing costs well under 1% of compressing even incompressible input at the fastest
+            // level, so it stays inline rather than overlapping on another thread.
             hasher.update(&input_buf[..read]);
             self.input.consume(read);
 
             output.write_all(&output_buf[..written])?;
+            bytes_written += written as u64;
+
+            unflushed += written;
+            if let Some(interval) = self.options.flush_interval
+                && unflushed >= interval
+            {
+                output.flush()?;
+                unflushed = 0;
+            }
+
+            if self.options.adaptive_buffer && written == capacity {
+                full_chunks += 1;
+                if full_chunks == GROW_AFTER_FULL_CHUNKS
+                    && capacity < self.options.adaptive_buffer_cap
+                {
+                    heap_buf.resize((capacity * 2).min(self.options.adaptive_buffer_cap), 0);
+                    full_chunks = 0;
+                }
+            } else {
+                full_chunks = 0;
+            }
 
             if eof && written == 0 {
                 self.crc32 = hasher.finalize();
                 self.uncompressed_size = stream.total_in();
-                self.compressed_size = stream.total_out();
+                self.compressed_size = bytes_written;
                 break;
             }
         }
 
+        // The trailer's member size must describe what actually went to the output.
+        debug_assert_eq!(bytes_written, stream.total_out());
+        if bytes_written != stream.total_out() {
+            return Err(LzipError::InvalidMemberSize);
+        }
+
         Ok(())
     }
 
     /// Write the lzip trailer to the output stream.
-    fn write_trailer<W: Write>(&self, output: &mut W) -> Result<(), LzipError> {
-        let mut trailer = [0; 20];
+    fn write_trailer<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
+        let trailer = Trailer::new(self.crc32, self.uncompressed_size, self.compressed_size);
+        output.write_all(&trailer.to_bytes())?;
+        self.written += Trailer::SIZE as u64;
+        self.record_member(trailer.member_size, self.uncompressed_size);
 
-        let member_size = 6 + self.compressed_size + 20; // 6 bytes for header, 20 bytes for trailer
+        Ok(())
+    }
 
-        trailer[0..4].copy_from_slice(&self.crc32.to_le_bytes());
-        trailer[4..12].copy_from_slice(&self.uncompressed_size.to_le_bytes());
-        trailer[12..20].copy_from_slice(&member_size.to_le_bytes());
+    /// Reserves the memory of compressing a member with `resolved` from the memory budget, if
+    /// there is one.
+    fn reserve_memory(&self, resolved: &ResolvedOptions) -> Result<Option<Reservation>, LzipError> {
+        let Some(budget) = &self.options.memory_budget else {
+            return Ok(None);
+        };
 
-        output.write_all(&trailer)?;
+        let output_buffer = if self.options.adaptive_buffer {
+            self.options.adaptive_buffer_cap
+        } else {
+            OUTPUT_BUFFER_SIZE
+        };
+        let buffers = (output_buffer + self.input.capacity()) as u64;
+        let usage = lzma1::encoder_memusage(resolved)?.saturating_add(buffers);
 
-        Ok(())
+        budget.reserve(usage).map(Some)
     }
+}
 
-    fn dict_size(&self) -> u32 {
-        let base: u32 = match self.compression_level {
-            CompressionLevel::Fastest => 18,
-            CompressionLevel::Fast => 22,
-            CompressionLevel::Default => 23,
-            CompressionLevel::Maximum => 26,
-        };
+/// The input of an [`Encoder`], yielding the bytes read ahead by
+/// [`EncoderOptions::probe_size`] or for the sample of
+/// [`EncoderOptions::store_if_incompressible`] before the rest of the input.
+struct ProbedReader<R: Read> {
+    /// The bytes read ahead.
+    probe: Vec<u8>,
+    /// The position of the first unread byte in `probe`.
+    pos: usize,
+    /// Whether the input has been probed.
+    probed: bool,
+    /// The input data stream.
+    inner: R,
+}
+
+impl<R: Read> Read for ProbedReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.pos < self.probe.len() {
+            let n = (self.probe.len() - self.pos).min(buf.len());
+            buf[..n].copy_from_slice(&self.probe[self.pos..self.pos + n]);
+            self.pos += n;
+            if self.pos == self.probe.len() {
+                self.probe = Vec::new();
+                self.pos = 0;
+            }
+            return Ok(n);
+        }
 
-        return 1 << base;
+        self.inner.read(buf)
     }
+}
 
-    /// Encodes the dictionary size to a single byte.
-    fn encode_dict_size(dict_size: u32) -> u8 {
-        let mut ds = ((dict_size - 1).ilog2() + 1) as u8;
+impl Encoder<ChannelReader> {
+    /// Creates a new `Encoder` instance reading its input from a channel.
+    ///
+    /// Every buffer received from `rx` is compressed as more input of the same member. The
+    /// member is finished once all senders have been dropped and the channel is drained.
+    pub fn from_receiver(rx: Receiver<Vec<u8>>, options: EncoderOptions) -> Self {
+        Self::new_with_options(ChannelReader::new(rx), options)
+    }
+}
 
-        if dict_size > MIN_DICT_SIZE {
-            let base: u32 = 1 << ds;
-            let frac: u32 = base / 16;
+/// A reader draining a channel of byte buffers.
+///
+/// The reader reaches end of file once all senders of the channel have been dropped and every
+/// received buffer has been read.
+pub struct ChannelReader {
+    /// The channel to receive buffers from.
+    rx: Receiver<Vec<u8>>,
+    /// The buffer currently being read.
+    chunk: Vec<u8>,
+    /// The position of the first unread byte in `chunk`.
+    pos: usize,
+}
 
-            for i in (1..=7).rev() {
-                if (base - (i * frac)) >= dict_size {
-                    ds |= (i as u8) << 5;
-                    break;
+impl ChannelReader {
+    /// Creates a new `ChannelReader` draining `rx`.
+    pub fn new(rx: Receiver<Vec<u8>>) -> Self {
+        ChannelReader {
+            rx,
+            chunk: Vec::new(),
+            pos: 0,
+        }
+    }
+}
+
+impl Read for ChannelReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos >= self.chunk.len() {
+            match self.rx.recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.pos = 0;
                 }
+                Err(_) => return Ok(0),
             }
         }
 
-        return ds;
+        let n = (self.chunk.len() - self.pos).min(buf.len());
+        buf[..n].copy_from_slice(&self.chunk[self.pos..self.pos + n]);
+        self.pos += n;
+
+        Ok(n)
     }
 }
+
+/// Compresses `data` into a single lzip member, reusing its allocation while compressing.
+///
+/// The input `Vec` is consumed. Compressed bytes are written back over the part of the input
+/// that liblzma has already consumed, so no second buffer of the input's size is needed. If the
+/// compressed output outgrows the consumed input (e.g. for incompressible data), the remainder
+/// is appended and the `Vec` grows as needed. The spare capacity left by compressible data is
+/// released at the end, so the result doesn't hold on to the uncompressed size. Whether that
+/// keeps the allocation in place or moves the result to a smaller one is up to the allocator.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::{CompressionLevel, compress_in_place};
+///
+/// let data = vec![0u8; 64 * 1024];
+/// let compressed = compress_in_place(data, CompressionLevel::Default).expect("failed to encode");
+/// assert!(compressed.len() < 64 * 1024);
+/// ```
+pub fn compress_in_place(mut data: Vec<u8>, level: CompressionLevel) -> Result<Vec<u8>, LzipError> {
+    let resolved = EncoderOptions::default().level(level).resolve()?;
+    let dict_size = resolved.dict_size;
+
+    let mut stream = new_raw_encoder(&resolved)?;
+    let mut hasher = Hasher::new();
+
+    let mut output_buf = [0u8; OUTPUT_BUFFER_SIZE];
+    // Compressed bytes which could not yet be written back into `data`.
+    let mut pending: Vec<u8> = Header::new(dict_size).to_bytes()?.to_vec();
+    let mut read_pos = 0;
+    let mut write_pos = 0;
+
+    loop {
+        let eof = read_pos == data.len();
+
+        let before_out = stream.total_out();
+        let before_in = stream.total_in();
+        let status = stream.process(
+            &data[read_pos..],
+            &mut output_buf,
+            if eof { Action::Finish } else { Action::Run },
+        )?;
+        let read = processed(before_in, stream.total_in(), data.len() - read_pos)?;
+        let written = processed(before_out, stream.total_out(), output_buf.len())?;
+
+        hasher.update(&data[read_pos..read_pos + read]);
+        read_pos += read;
+
+        // Only bytes that liblzma has already consumed may be overwritten.
+        pending.extend_from_slice(&output_buf[..written]);
+        let n = pending.len().min(read_pos - write_pos);
+        data[write_pos..write_pos + n].copy_from_slice(&pending[..n]);
+        pending.drain(..n);
+        write_pos += n;
+
+        if status == Status::StreamEnd {
+            break;
+        }
+    }
+
+    let trailer = Trailer::new(hasher.finalize(), stream.total_in(), stream.total_out()).to_bytes();
+    pending.extend_from_slice(&trailer);
+
+    data.truncate(write_pos);
+    data.extend_from_slice(&pending);
+    data.shrink_to_fit();
+
+    Ok(data)
+}
+
+/// Returns the dictionary size used for the given compression level.
+fn level_dict_size(level: CompressionLevel) -> u32 {
+    let base: u32 = match level {
+        CompressionLevel::Fastest => 18,
+        CompressionLevel::Fast => 22,
+        CompressionLevel::Default => 23,
+        CompressionLevel::Maximum => 26,
+    };
+
+    1 << base
+}
+
+/// Returns the nice length of the given liblzma preset.
+fn preset_nice_len(preset: u32) -> u32 {
+    match preset {
+        0 | 1 => 128,
+        2 | 3 => 273,
+        4 => 16,
+        5 => 32,
+        _ => 64,
+    }
+}
+
+/// Returns the compression level which uses the given dictionary size, if any.
+pub(crate) fn level_for_dict_size(dict_size: u32) -> Option<CompressionLevel> {
+    [
+        CompressionLevel::Fastest,
+        CompressionLevel::Fast,
+        CompressionLevel::Default,
+        CompressionLevel::Maximum,
+    ]
+    .into_iter()
+    .find(|&level| level_dict_size(level) == dict_size)
+}
+
+/// Returns the output capacity to reserve for compressing `input_size` bytes.
+fn reserve_hint(input_size: u64) -> usize {
+    let hint = input_size / 2 + member_size(0);
+    usize::try_from(hint).unwrap_or(usize::MAX)
+}
+
+/// Returns the smallest dictionary size that decodes a member of `uncompressed_size` bytes.
+///
+/// No match can reach further back than the start of the member, so a dictionary holding the
+/// whole uncompressed data is always enough. This is the smallest size representable in the
+/// lzip header not below `uncompressed_size`, i.e. a power of two minus up to seven sixteenths of
+/// it, clamped to between 4 KiB and 512 MiB. It is also the size the encoder declares when
+/// [`EncoderOptions::auto_dict`] shrinks the dictionary to the input. The dictionary size
+/// declared by a member is enough as well, so the smaller of the two is what decoding really
+/// needs.
+pub fn min_dict_size_for(uncompressed_size: u64) -> u32 {
+    let dict_size = uncompressed_size.clamp(MIN_DICT_SIZE as u64, MAX_DICT_SIZE as u64) as u32;
+    let rounded = encode_dict_size(dict_size).and_then(decode_dict_size);
+    rounded.expect("a clamped dictionary size is always representable")
+}
+
+/// Returns the estimated memory in bytes needed to compress with `options`.
+///
+/// This is the memory liblzma reports for the LZMA encoder of the resolved settings (see
+/// [`EncoderOptions::resolve`]), mostly taken by the match finder and the dictionary, times the
+/// number of [threads](EncoderOptions::threads) compressing in parallel. The buffers holding
+/// blocks and their compressed members aren't included. Returns the errors of
+/// [`EncoderOptions::resolve`].
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::{CompressionLevel, EncoderOptions, encoder_memory_usage};
+///
+/// let options = EncoderOptions::default().level(CompressionLevel::Maximum);
+/// let usage = encoder_memory_usage(&options).expect("invalid options");
+/// assert!(usage > 64 << 20);
+/// ```
+pub fn encoder_memory_usage(options: &EncoderOptions) -> Result<u64, LzipError> {
+    let resolved = options.resolve()?;
+    let usage = lzma1::encoder_memusage(&resolved)?;
+
+    Ok(usage.saturating_mul(resolved.threads as u64))
+}
+
+/// Encodes the dictionary size to the single byte stored in the lzip header.
+///
+/// Sizes which are not exactly representable are rounded up to the next representable size.
+/// Returns [`LzipError::InvalidDictSize`] if the size is not between 4 KiB and 512 MiB.
+pub fn encode_dict_size(dict_size: u32) -> Result<u8, LzipError> {
+    if !(MIN_DICT_SIZE..=MAX_DICT_SIZE).contains(&dict_size) {
+        return Err(LzipError::InvalidDictSize);
+    }
+
+    let mut ds = ((dict_size - 1).ilog2() + 1) as u8;
+
+    if dict_size > MIN_DICT_SIZE {
+        let base: u32 = 1 << ds;
+        let frac: u32 = base / 16;
+
+        for i in (1..=7).rev() {
+            if (base - (i * frac)) >= dict_size {
+                ds |= (i as u8) << 5;
+                break;
+            }
+        }
+    }
+
+    Ok(ds)
+}
@@ -3,11 +3,12 @@
 
 //! The error module for the lzipper crate.
 
-use std::{error, fmt, io};
+use std::sync::Arc;
+use std::{error, fmt, io, mem};
 
 use liblzma::stream;
 
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug)]
 /// An error type for the lzipper crate.
 pub enum LzipError {
     /// An error indicating that the magic number is invalid.
@@ -25,15 +26,77 @@ pub enum LzipError {
     InvalidDataSize,
     /// An error indicating that the size of the member is invalid.
     InvalidMemberSize,
+    /// An error indicating that the operation was cancelled through its cancel token.
+    Cancelled,
+    /// An error indicating that the data expanded beyond the maximum decompression ratio.
+    RatioExceeded,
+    /// An error indicating that compression didn't finish within its time budget.
+    TimeBudgetExceeded,
+    /// An error indicating that the memory an operation needs would exceed its memory budget.
+    MemoryLimitExceeded,
+    /// An error indicating that the compressed member is larger than the uncompressed data.
+    Expanded,
+    /// An error indicating that a member failed, wrapping its zero-based index and the error.
+    MemberError(u64, Box<LzipError>),
+    /// An error indicating that the compressed data of a member is corrupt, wrapping where the
+    /// corruption was detected.
+    ///
+    /// LZMA stream errors are reported at the offset where the stream stopped decoding, and
+    /// trailer mismatches at the offset of the trailer. The corruption itself may lie somewhat
+    /// before the offset, as it can take a while to surface.
+    Corrupt {
+        /// The zero-based index of the member.
+        member: u64,
+        /// The offset in the compressed input, headers and trailers included.
+        offset: u64,
+        /// The underlying error.
+        error: Box<LzipError>,
+    },
     /// An error indicating that the LZMA stream encountered an error.
     StreamError(stream::Error),
     /// An error indicating that an I/O operation failed.
     /// This error wraps the underlying `io::Error`.
     /// This can occur during reading or writing operations.
-    IoError(io::ErrorKind),
+    IoError(Arc<io::Error>),
 }
 
-impl error::Error for LzipError {}
+impl error::Error for LzipError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            LzipError::MemberError(_, err) => Some(err.as_ref()),
+            LzipError::Corrupt { error, .. } => Some(error.as_ref()),
+            LzipError::StreamError(err) => Some(err),
+            LzipError::IoError(err) => Some(err.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+/// I/O errors are compared by their kind, as `io::Error` itself can't be compared.
+impl PartialEq for LzipError {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (LzipError::MemberError(a, err_a), LzipError::MemberError(b, err_b)) => {
+                a == b && err_a == err_b
+            }
+            (
+                LzipError::Corrupt {
+                    member: a,
+                    offset: offset_a,
+                    error: err_a,
+                },
+                LzipError::Corrupt {
+                    member: b,
+                    offset: offset_b,
+                    error: err_b,
+                },
+            ) => a == b && offset_a == offset_b && err_a == err_b,
+            (LzipError::StreamError(a), LzipError::StreamError(b)) => a == b,
+            (LzipError::IoError(a), LzipError::IoError(b)) => a.kind() == b.kind(),
+            (a, b) => mem::discriminant(a) == mem::discriminant(b),
+        }
+    }
+}
 
 impl fmt::Display for LzipError {
     #[rustfmt::skip]
@@ -46,6 +109,13 @@ impl fmt::Display for LzipError {
             LzipError::InvalidCrc => write!(f, "invalid CRC32 checksum"),
             LzipError::InvalidDataSize => write!(f, "invalid size of uncompressed data"),
             LzipError::InvalidMemberSize => write!(f, "invalid size of member"),
+            LzipError::Cancelled => write!(f, "operation cancelled"),
+            LzipError::RatioExceeded => write!(f, "maximum decompression ratio exceeded"),
+            LzipError::TimeBudgetExceeded => write!(f, "time budget exceeded"),
+            LzipError::MemoryLimitExceeded => write!(f, "memory limit exceeded"),
+            LzipError::Expanded => write!(f, "compressed data is larger than uncompressed data"),
+            LzipError::MemberError(index, err) => write!(f, "member {}: {}", index, err),
+            LzipError::Corrupt { member, offset, error } => write!(f, "member {} at offset {}: {}", member, offset, error),
             LzipError::StreamError(err) => write!(f, "{}", err),
             LzipError::IoError(err) => write!(f, "{}", err),
         }
@@ -54,7 +124,7 @@ impl fmt::Display for LzipError {
 
 impl From<io::Error> for LzipError {
     fn from(value: io::Error) -> Self {
-        LzipError::IoError(value.kind())
+        LzipError::IoError(Arc::new(value))
     }
 }
 
@@ -63,3 +133,14 @@ impl From<stream::Error> for LzipError {
         LzipError::StreamError(value)
     }
 }
+
+impl From<LzipError> for io::Error {
+    fn from(value: LzipError) -> Self {
+        match value {
+            LzipError::IoError(err) => {
+                Arc::try_unwrap(err).unwrap_or_else(|err| io::Error::new(err.kind(), err))
+            }
+            err => io::Error::new(io::ErrorKind::InvalidData, err),
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! The header and trailer framing every lzip member.
+
+use crate::{LZIP_MAGIC, LZIP_VERSION, LzipError, decode_dict_size, encode_dict_size};
+
+/// The header at the start of every lzip member.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::Header;
+///
+/// let bytes = Header::new(1 << 23).to_bytes().expect("failed to build header");
+/// let header = Header::from_bytes(&bytes).expect("failed to parse header");
+/// assert_eq!(header.dict_size, 1 << 23);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Header {
+    /// The lzip version.
+    pub version: u8,
+    /// The dictionary size needed to decompress the member.
+    pub dict_size: u32,
+}
+
+impl Header {
+    /// The size of the header in bytes.
+    pub const SIZE: usize = 6;
+
+    /// Creates a header for the current lzip version with the given dictionary size.
+    pub fn new(dict_size: u32) -> Self {
+        Header {
+            version: LZIP_VERSION,
+            dict_size,
+        }
+    }
+
+    /// Encodes the header to bytes.
+    ///
+    /// The version is written as is, so headers for unsupported versions can be built too. The
+    /// dictionary size is rounded up to the next size the header can represent. Returns
+    /// [`LzipError::InvalidDictSize`] if it is not between 4 KiB and 512 MiB.
+    pub fn to_bytes(&self) -> Result<[u8; Self::SIZE], LzipError> {
+        let mut header = [0; Self::SIZE];
+
+        header[0..4].copy_from_slice(&LZIP_MAGIC);
+        header[4] = self.version;
+        header[5] = encode_dict_size(self.dict_size)?;
+
+        Ok(header)
+    }
+
+    /// Parses a header from bytes.
+    ///
+    /// Returns [`LzipError::InvalidMagic`], [`LzipError::UnsupportedVersion`] or
+    /// [`LzipError::InvalidDictSize`] if the header is not one the decoder accepts.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Result<Self, LzipError> {
+        Self::parse(bytes, TrailerFormat::Version1)
+    }
+
+    /// Parses a header, accepting only the version that uses the given trailer format.
+    pub(crate) fn parse(
+        bytes: &[u8; Self::SIZE],
+        format: TrailerFormat,
+    ) -> Result<Self, LzipError> {
+        if bytes[0..4] != LZIP_MAGIC {
+            return Err(LzipError::InvalidMagic);
+        }
+
+        if bytes[4] != format.version() {
+            return Err(LzipError::UnsupportedVersion);
+        }
+
+        Ok(Header {
+            version: bytes[4],
+            dict_size: decode_dict_size(bytes[5])?,
+        })
+    }
+}
+
+/// The trailer at the end of every lzip member.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::Trailer;
+///
+/// let trailer = Trailer::new(0x3610_a686, 6, 12);
+/// assert_eq!(trailer.member_size, 38);
+/// assert_eq!(Trailer::from_bytes(&trailer.to_bytes()), trailer);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Trailer {
+    /// The CRC32 of the uncompressed data.
+    pub crc32: u32,
+    /// The size of the uncompressed data.
+    pub uncompressed_size: u64,
+    /// The size of the whole member, including the header and trailer.
+    pub member_size: u64,
+}
+
+impl Trailer {
+    /// The size of the trailer in bytes.
+    pub const SIZE: usize = 20;
+
+    /// Creates a trailer for a member with the given size of LZMA data.
+    ///
+    /// The member size is the `compressed_size` plus the size of the header and trailer.
+    pub fn new(crc32: u32, uncompressed_size: u64, compressed_size: u64) -> Self {
+        Trailer {
+            crc32,
+            uncompressed_size,
+            member_size: member_size(compressed_size),
+        }
+    }
+
+    /// Encodes the trailer to bytes.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut trailer = [0; Self::SIZE];
+
+        trailer[0..4].copy_from_slice(&self.crc32.to_le_bytes());
+        trailer[4..12].copy_from_slice(&self.uncompressed_size.to_le_bytes());
+        trailer[12..20].copy_from_slice(&self.member_size.to_le_bytes());
+
+        trailer
+    }
+
+    /// Parses a trailer from bytes.
+    ///
+    /// Any bytes form a trailer; its fields are only verified against the member's data.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Self {
+        Trailer {
+            crc32: u32::from_le_bytes(bytes[0..4].try_into().unwrap()),
+            uncompressed_size: u64::from_le_bytes(bytes[4..12].try_into().unwrap()),
+            member_size: u64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+        }
+    }
+}
+
+/// The layout of the member trailer, which depends on the lzip version.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TrailerFormat {
+    /// The 20 byte trailer of version 1, holding the CRC32, the uncompressed size and the
+    /// member size. This is the current format.
+    #[default]
+    Version1,
+    /// The 12 byte trailer of the obsolete version 0, written by early lzip releases, holding
+    /// only the CRC32 and the uncompressed size.
+    Version0,
+}
+
+impl TrailerFormat {
+    /// Returns the version stored in the headers of members using this trailer format.
+    pub(crate) fn version(self) -> u8 {
+        match self {
+            TrailerFormat::Version1 => LZIP_VERSION,
+            TrailerFormat::Version0 => 0,
+        }
+    }
+
+    /// Returns the size of the trailer in bytes.
+    pub(crate) fn size(self) -> usize {
+        match self {
+            TrailerFormat::Version1 => Trailer::SIZE,
+            TrailerFormat::Version0 => 12,
+        }
+    }
+}
+
+/// Returns the size of a member with the given size of LZMA data.
+pub(crate) fn member_size(compressed_size: u64) -> u64 {
+    Header::SIZE as u64 + compressed_size + Trailer::SIZE as u64
+}
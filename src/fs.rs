@@ -0,0 +1,229 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Compresses and decompresses whole files, the way the `lzip` tool does.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use lzipper::fs::{compress_file, decompress_file};
+//! use lzipper::{DecodeOptions, EncoderOptions};
+//! use std::path::Path;
+//!
+//! let compressed = compress_file(Path::new("notes.txt"), None, EncoderOptions::default(), false)
+//!     .expect("failed to compress");
+//! assert_eq!(compressed, Path::new("notes.txt.lz"));
+//!
+//! let decompressed = decompress_file(&compressed, None, DecodeOptions::default(), false)
+//!     .expect("failed to decompress");
+//! assert_eq!(decompressed, Path::new("notes.txt"));
+//! ```
+
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use crate::decoder::CONCATENATED;
+use crate::{DecodeOptions, Decoder, Encoder, EncoderOptions, LzipError};
+
+/// Compresses the file at `src` into a new file, returning the path it was written to.
+///
+/// The compressed file is written to `dst`, or to `src` with `.lz` appended if `dst` is
+/// `None`, and must not exist yet. Once written, the file is synced to disk and read back: it
+/// has to decompress without error to as many bytes as `src` holds. Only then is `src`
+/// removed, unless `keep_source` is set. The file is written through an [`AtomicWriter`], so
+/// it only appears at `dst` once complete; if anything fails, no file is left at `dst` and
+/// `src` is left untouched.
+pub fn compress_file(
+    src: &Path,
+    dst: Option<&Path>,
+    options: EncoderOptions,
+    keep_source: bool,
+) -> Result<PathBuf, LzipError> {
+    let dst = dst.map_or_else(|| compressed_name(src), Path::to_path_buf);
+
+    let input = File::open(src)?;
+    let input_size = input.metadata()?.len();
+    let mut output = AtomicWriter::create_new(&dst)?;
+    Encoder::new_with_options(input, options.input_size(input_size)).encode(&mut output)?;
+    output.commit()?;
+
+    let result = (|| {
+        let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+        let mut decoder = Decoder::new_with_options(File::open(&dst)?, options);
+        if decoder.total_uncompressed_size()? != input_size {
+            return Err(LzipError::InvalidDataSize);
+        }
+        decoder.test()?;
+
+        Ok(())
+    })();
+
+    finish(src, &dst, result, keep_source)
+}
+
+/// Decompresses the lzip file at `src` into a new file, returning the path it was written to.
+///
+/// The decompressed file is written to `dst` and must not exist yet. If `dst` is `None`, it is
+/// named after `src` the way `lzip` names it: a `.lz` extension is removed, `.tlz` becomes
+/// `.tar`, and `.out` is appended to any other name. All members are decompressed, whatever
+/// the flags in `options`. Every member is verified against its trailer while decoding, and
+/// the file is synced to disk before `src` is removed, unless `keep_source` is set. The file
+/// is written through an [`AtomicWriter`]; if anything fails, no file is left at `dst` and
+/// `src` is left untouched.
+pub fn decompress_file(
+    src: &Path,
+    dst: Option<&Path>,
+    options: DecodeOptions,
+    keep_source: bool,
+) -> Result<PathBuf, LzipError> {
+    let dst = dst.map_or_else(|| decompressed_name(src), Path::to_path_buf);
+
+    let input = File::open(src)?;
+    let mut output = AtomicWriter::create_new(&dst)?;
+    Decoder::new_with_options(input, options.add_flags(CONCATENATED)).decode(&mut output)?;
+    output.commit()?;
+
+    if !keep_source {
+        fs::remove_file(src)?;
+    }
+
+    Ok(dst)
+}
+
+/// A file writer which only puts the file in place once it is complete.
+///
+/// The data is written to a temporary file next to the destination, which
+/// [`AtomicWriter::commit`] syncs to disk and renames to the destination in one step. If the
+/// writer is dropped without being committed, e.g. because encoding failed partway, the
+/// temporary file is removed. The destination therefore either holds the complete output or
+/// is left as it was, and a partial `.lz` file is never mistaken for a valid one.
+///
+/// # Example
+///
+/// ```no_run
+/// use lzipper::fs::AtomicWriter;
+/// use lzipper::Encoder;
+/// use std::path::Path;
+///
+/// let mut output = AtomicWriter::create(Path::new("notes.txt.lz")).expect("failed to create");
+/// Encoder::new(b"some notes".as_slice())
+///     .encode(&mut output)
+///     .expect("failed to encode");
+/// output.commit().expect("failed to commit");
+/// ```
+#[derive(Debug)]
+pub struct AtomicWriter {
+    /// The temporary file, until the writer is committed.
+    file: Option<BufWriter<File>>,
+    /// The path of the temporary file.
+    temp_path: PathBuf,
+    /// The path the file is renamed to on commit.
+    path: PathBuf,
+}
+
+impl AtomicWriter {
+    /// Creates a writer for a file at `path`, replacing any existing file there on commit.
+    pub fn create(path: &Path) -> Result<Self, LzipError> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidInput))?;
+
+        let mut temp_name = OsString::from(".");
+        temp_name.push(name);
+        temp_name.push(format!(".{}.tmp", process::id()));
+        let temp_path = path.with_file_name(temp_name);
+
+        let file = File::create_new(&temp_path)?;
+        Ok(AtomicWriter {
+            file: Some(BufWriter::new(file)),
+            temp_path,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Creates a writer for a file at `path`, failing if a file exists there already.
+    ///
+    /// The check is made up front; a file created at `path` before the commit is replaced.
+    pub fn create_new(path: &Path) -> Result<Self, LzipError> {
+        if path.try_exists()? {
+            return Err(io::Error::from(io::ErrorKind::AlreadyExists).into());
+        }
+
+        Self::create(path)
+    }
+
+    /// Syncs the written data to disk and moves the file to its destination.
+    pub fn commit(mut self) -> Result<(), LzipError> {
+        let file = self.file.take().expect("writer already committed");
+        file.into_inner()
+            .map_err(|err| err.into_error())?
+            .sync_all()?;
+        fs::rename(&self.temp_path, &self.path)?;
+
+        Ok(())
+    }
+
+    /// Returns the open temporary file.
+    fn file(&mut self) -> &mut BufWriter<File> {
+        self.file.as_mut().expect("writer already committed")
+    }
+}
+
+impl Write for AtomicWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.file().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file().flush()
+    }
+}
+
+impl Drop for AtomicWriter {
+    fn drop(&mut self) {
+        // Fails harmlessly once a commit has renamed the file away.
+        let _ = fs::remove_file(&self.temp_path);
+    }
+}
+
+/// Removes either the source once the destination is verified, or the destination.
+fn finish(
+    src: &Path,
+    dst: &Path,
+    result: Result<(), LzipError>,
+    keep_source: bool,
+) -> Result<PathBuf, LzipError> {
+    if let Err(err) = result {
+        let _ = fs::remove_file(dst);
+        return Err(err);
+    }
+
+    if !keep_source {
+        fs::remove_file(src)?;
+    }
+
+    Ok(dst.to_path_buf())
+}
+
+/// Returns the name of the compressed file for `path`.
+fn compressed_name(path: &Path) -> PathBuf {
+    let mut name = OsString::from(path);
+    name.push(".lz");
+    name.into()
+}
+
+/// Returns the name of the decompressed file for `path`.
+fn decompressed_name(path: &Path) -> PathBuf {
+    match path.extension().and_then(|ext| ext.to_str()) {
+        Some("lz") => path.with_extension(""),
+        Some("tlz") => path.with_extension("tar"),
+        _ => {
+            let mut name = OsString::from(path);
+            name.push(".out");
+            name.into()
+        }
+    }
+}
@@ -0,0 +1,145 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! An optional index of member locations, appended after the last member.
+//!
+//! The index is a lzipper extension and not part of the lzip format. It starts with a magic
+//! whose first byte can't start a member, so lzip treats it as trailing data: ignored by
+//! default and rejected with `--trailing-error`. The layout, with all integers little endian,
+//! is:
+//!
+//! ```text
+//! +-------+-------+------------------------------------------+-------+------------+-------+
+//! | magic | count | count x (offset, member size, data size) | crc32 | index size | magic |
+//! | 6 B   | 8 B   | count x 24 B                             | 4 B   | 8 B        | 6 B   |
+//! +-------+-------+------------------------------------------+-------+------------+-------+
+//! ```
+//!
+//! The CRC32 covers everything before it, and the index size is the size of the whole index,
+//! so it can be found by reading the end of the file.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use crc32fast::Hasher;
+
+use crate::LzipError;
+
+/// The magic at both ends of the index. `0xFF` never starts an lzip member.
+pub(crate) const INDEX_MAGIC: [u8; 6] = [0xFF, b'L', b'Z', b'I', b'D', b'X'];
+
+/// The size of the count following the leading magic.
+const HEAD_SIZE: usize = INDEX_MAGIC.len() + 8;
+/// The size of each entry.
+const ENTRY_SIZE: usize = 24;
+/// The size of the CRC32, index size and magic closing the index.
+const FOOTER_SIZE: usize = 4 + 8 + INDEX_MAGIC.len();
+
+/// The location and size of a member, as recorded in the index.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexEntry {
+    /// The offset of the member from the start of the encoder's output.
+    pub offset: u64,
+    /// The size of the whole member, including the header and trailer.
+    pub member_size: u64,
+    /// The size of the member's uncompressed data.
+    pub uncompressed_size: u64,
+}
+
+/// Encodes an index describing `entries`.
+pub(crate) fn encode_index(entries: &[IndexEntry]) -> Vec<u8> {
+    let size = HEAD_SIZE + entries.len() * ENTRY_SIZE + FOOTER_SIZE;
+    let mut index = Vec::with_capacity(size);
+
+    index.extend_from_slice(&INDEX_MAGIC);
+    index.extend_from_slice(&(entries.len() as u64).to_le_bytes());
+    for entry in entries {
+        index.extend_from_slice(&entry.offset.to_le_bytes());
+        index.extend_from_slice(&entry.member_size.to_le_bytes());
+        index.extend_from_slice(&entry.uncompressed_size.to_le_bytes());
+    }
+
+    let mut hasher = Hasher::new();
+    hasher.update(&index);
+    index.extend_from_slice(&hasher.finalize().to_le_bytes());
+    index.extend_from_slice(&(size as u64).to_le_bytes());
+    index.extend_from_slice(&INDEX_MAGIC);
+
+    index
+}
+
+/// Returns the number of bytes following the leading magic of an index with `count` entries.
+pub(crate) fn index_body_size(count: u64) -> Option<u64> {
+    count
+        .checked_mul(ENTRY_SIZE as u64)?
+        .checked_add(FOOTER_SIZE as u64)
+}
+
+/// Returns the size of the index at the end of the input, or `None` if it has none.
+///
+/// Only the footer is checked, the position of the input is left at an unspecified offset.
+pub(crate) fn index_size<R: Read + Seek>(
+    input: &mut R,
+    start: u64,
+) -> Result<Option<u64>, LzipError> {
+    let end = input.seek(SeekFrom::End(0))?;
+    if end.saturating_sub(start) < (HEAD_SIZE + FOOTER_SIZE) as u64 {
+        return Ok(None);
+    }
+
+    let mut footer = [0; FOOTER_SIZE];
+    input.seek(SeekFrom::Start(end - FOOTER_SIZE as u64))?;
+    input.read_exact(&mut footer)?;
+    if footer[12..] != INDEX_MAGIC {
+        return Ok(None);
+    }
+
+    let size = u64::from_le_bytes(footer[4..12].try_into().unwrap());
+    let entries = size.checked_sub((HEAD_SIZE + FOOTER_SIZE) as u64);
+    match entries {
+        Some(entries) if entries % ENTRY_SIZE as u64 == 0 && size <= end - start => Ok(Some(size)),
+        _ => Err(LzipError::InvalidMemberSize),
+    }
+}
+
+/// Reads the index at the end of the input, or returns `None` if it has none.
+///
+/// The position of the input is left at an unspecified offset.
+pub(crate) fn read_index<R: Read + Seek>(
+    input: &mut R,
+    start: u64,
+) -> Result<Option<Vec<IndexEntry>>, LzipError> {
+    let Some(size) = index_size(input, start)? else {
+        return Ok(None);
+    };
+
+    let end = input.seek(SeekFrom::End(0))?;
+    let mut index = vec![0; size as usize];
+    input.seek(SeekFrom::Start(end - size))?;
+    input.read_exact(&mut index)?;
+
+    let crc_offset = index.len() - FOOTER_SIZE;
+    let count = u64::from_le_bytes(index[6..HEAD_SIZE].try_into().unwrap());
+    if index[..INDEX_MAGIC.len()] != INDEX_MAGIC
+        || count != ((crc_offset - HEAD_SIZE) / ENTRY_SIZE) as u64
+    {
+        return Err(LzipError::InvalidMemberSize);
+    }
+
+    let mut hasher = Hasher::new();
+    hasher.update(&index[..crc_offset]);
+    let crc32 = u32::from_le_bytes(index[crc_offset..crc_offset + 4].try_into().unwrap());
+    if hasher.finalize() != crc32 {
+        return Err(LzipError::InvalidCrc);
+    }
+
+    let entries = index[HEAD_SIZE..crc_offset]
+        .chunks_exact(ENTRY_SIZE)
+        .map(|entry| IndexEntry {
+            offset: u64::from_le_bytes(entry[0..8].try_into().unwrap()),
+            member_size: u64::from_le_bytes(entry[8..16].try_into().unwrap()),
+            uncompressed_size: u64::from_le_bytes(entry[16..24].try_into().unwrap()),
+        })
+        .collect();
+
+    Ok(Some(entries))
+}
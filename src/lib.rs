@@ -28,14 +28,57 @@
 
 #![deny(missing_docs)]
 
+pub mod archive;
+#[cfg(feature = "tokio")]
+pub mod async_decoder;
+pub mod budget;
+pub mod compressor;
 pub mod decoder;
 pub mod encoder;
 pub mod error;
+pub mod format;
+#[cfg(feature = "fs")]
+pub mod fs;
+pub mod index;
+pub mod lzma1;
+pub mod pool;
+pub mod sink;
+pub mod stack;
 
 pub use crate::error::LzipError;
 
-pub use crate::decoder::Decoder;
-pub use crate::encoder::{CompressionLevel, Encoder};
+pub use crate::archive::{
+    quick_check, repack, rewrite_dict_size, split_members, transcode, uncompressed_size,
+};
+
+pub use crate::format::{Header, Trailer, TrailerFormat};
+
+pub use crate::index::IndexEntry;
+
+pub use crate::budget::MemoryBudget;
+
+pub use crate::pool::BufferPool;
+
+pub use crate::sink::OutputSink;
+
+pub use crate::stack::{StackDecoder, StackEncoder};
+
+#[cfg(feature = "tokio")]
+pub use crate::async_decoder::AsyncDecoder;
+
+pub use crate::compressor::Compressor;
+
+pub use crate::decoder::{
+    DecodeFailure, DecodeOptions, DecodeProgress, DecodeStats, DecodeWarning, Decoder,
+    DecoderState, FrameInput, MaybeDecoder, MemberErrorPolicy, MemberInfo, ResultIterReader,
+    decode_dict_size, decoder_memory_usage,
+};
+pub use crate::encoder::{
+    ChannelReader, CompressionLevel, EncodeStats, Encoder, EncoderOptions, KnownLength,
+    ResolvedOptions, compress_in_place, encode_dict_size, encoder_memory_usage, min_dict_size_for,
+};
+
+use liblzma::stream;
 
 pub(crate) const MIN_DICT_SIZE: u32 = 1 << 12; // 4 KiB
 pub(crate) const MAX_DICT_SIZE: u32 = 1 << 29; // 512 MiB
@@ -44,3 +87,19 @@ pub(crate) const LZIP_MAGIC: [u8; 4] = [0x4C, 0x5A, 0x49, 0x50];
 pub(crate) const LZIP_VERSION: u8 = 0x01;
 
 const LZMA_PRESET_DEFAULT: u32 = 6;
+
+/// Returns the number of bytes moved by a `Stream::process` call from the stream's counter
+/// before and after the call, given the length of the buffer they were moved from or into.
+///
+/// A counter that went backwards or moved more bytes than the buffer holds is reported as a
+/// stream error rather than being trusted for slicing.
+pub(crate) fn processed(before: u64, after: u64, len: usize) -> Result<usize, LzipError> {
+    let count = after.saturating_sub(before);
+
+    debug_assert!(after >= before && count <= len as u64);
+    if after < before || count > len as u64 {
+        return Err(LzipError::StreamError(stream::Error::Program));
+    }
+
+    Ok(count as usize)
+}
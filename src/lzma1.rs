@@ -0,0 +1,204 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Handles bare LZMA1 streams without the lzip container.
+//!
+//! The streams produced here have no header, trailer or CRC32 and always end with an end of
+//! stream marker, exactly like the data inside an lzip member.
+//!
+//! # Example
+//!
+//! ```rust
+//! use lzipper::{EncoderOptions, lzma1};
+//!
+//! let input = b"the quick brown fox jumps over the lazy dog";
+//! let options = EncoderOptions::default().dict_size(1 << 16);
+//!
+//! let encoded = lzma1::encode(input, &options).expect("failed to encode");
+//! let decoded = lzma1::decode(&encoded, 1 << 16).expect("failed to decode");
+//!
+//! assert_eq!(input, decoded.as_slice());
+//! ```
+
+use std::{mem, ptr};
+
+use liblzma::stream::{self, Action, Filters, LzmaOptions, Status, Stream};
+use liblzma_sys::{LZMA_FILTER_LZMA1, LZMA_VLI_UNKNOWN, lzma_filter, lzma_options_lzma};
+
+use crate::encoder::{EncoderOptions, ResolvedOptions};
+use crate::{LZMA_PRESET_DEFAULT, LzipError, MAX_DICT_SIZE, MIN_DICT_SIZE, processed};
+
+/// Compresses `data` into a bare LZMA1 stream.
+///
+/// The compression level, dictionary size and LZMA parameters are taken from `options`.
+pub fn encode(data: &[u8], options: &EncoderOptions) -> Result<Vec<u8>, LzipError> {
+    let stream = new_raw_encoder(&options.resolve()?)?;
+    process_all(stream, data)
+}
+
+/// Decompresses a bare LZMA1 stream which was compressed with the given dictionary size.
+///
+/// Any data after the end of stream marker is ignored.
+pub fn decode(data: &[u8], dict_size: u32) -> Result<Vec<u8>, LzipError> {
+    if !(MIN_DICT_SIZE..=MAX_DICT_SIZE).contains(&dict_size) {
+        return Err(LzipError::InvalidDictSize);
+    }
+
+    let stream = new_raw_decoder(dict_size, None)?;
+    process_all(stream, data)
+}
+
+/// The literal context, literal position and position bits of an LZMA1 stream.
+///
+/// lzip always uses lc=3, lp=0 and pb=2, which the presets set, and doesn't store them, so
+/// other values have to be agreed on out of band.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct LzmaParams {
+    /// The number of literal context bits.
+    pub(crate) lc: u32,
+    /// The number of literal position bits.
+    pub(crate) lp: u32,
+    /// The number of position bits.
+    pub(crate) pb: u32,
+}
+
+impl LzmaParams {
+    /// Sets the parameters on `options`.
+    fn apply(self, options: &mut LzmaOptions) {
+        options.literal_context_bits(self.lc);
+        options.literal_position_bits(self.lp);
+        options.position_bits(self.pb);
+    }
+}
+
+/// Creates a raw LZMA1 encoder stream with the resolved settings.
+pub(crate) fn new_raw_encoder(resolved: &ResolvedOptions) -> Result<Stream, LzipError> {
+    Ok(Stream::new_raw_encoder(&raw_encoder_filters(resolved)?)?)
+}
+
+/// Creates the filter chain of a raw LZMA1 encoder, which can create any number of streams.
+pub(crate) fn raw_encoder_filters(resolved: &ResolvedOptions) -> Result<Filters, LzipError> {
+    let mut options = LzmaOptions::new_preset(resolved.preset)?;
+    options.dict_size(resolved.dict_size);
+    options.nice_len(resolved.nice_len);
+    resolved.lzma_params().apply(&mut options);
+
+    let mut filters = Filters::new();
+    filters.lzma1(&options);
+
+    Ok(filters)
+}
+
+/// Creates a raw LZMA1 decoder stream, with the standard lzip parameters unless `params` is
+/// given.
+pub(crate) fn new_raw_decoder(
+    dict_size: u32,
+    params: Option<LzmaParams>,
+) -> Result<Stream, LzipError> {
+    let mut options = LzmaOptions::new_preset(LZMA_PRESET_DEFAULT)?;
+    options.dict_size(dict_size);
+    if let Some(params) = params {
+        params.apply(&mut options);
+    }
+
+    let mut filters = Filters::new();
+    filters.lzma1(&options);
+
+    Ok(Stream::new_raw_decoder(&filters)?)
+}
+
+/// Returns the memory in bytes liblzma needs for a raw LZMA1 encoder with the resolved
+/// settings.
+pub(crate) fn encoder_memusage(resolved: &ResolvedOptions) -> Result<u64, LzipError> {
+    let mut options = preset_options(resolved.preset)?;
+    options.dict_size = resolved.dict_size;
+    options.lc = resolved.lc;
+    options.lp = resolved.lp;
+    options.pb = resolved.pb;
+    options.nice_len = resolved.nice_len;
+
+    // SAFETY: `raw_memusage` passes a terminated filter chain pointing to live options.
+    raw_memusage(&mut options, |filters| unsafe {
+        liblzma_sys::lzma_raw_encoder_memusage(filters)
+    })
+}
+
+/// Returns the memory in bytes liblzma needs for a raw LZMA1 decoder with the given
+/// dictionary size.
+pub(crate) fn decoder_memusage(dict_size: u32) -> Result<u64, LzipError> {
+    let mut options = preset_options(LZMA_PRESET_DEFAULT)?;
+    options.dict_size = dict_size;
+
+    // SAFETY: `raw_memusage` passes a terminated filter chain pointing to live options.
+    raw_memusage(&mut options, |filters| unsafe {
+        liblzma_sys::lzma_raw_decoder_memusage(filters)
+    })
+}
+
+/// Returns the LZMA1 options of the given preset.
+fn preset_options(preset: u32) -> Result<lzma_options_lzma, LzipError> {
+    // SAFETY: The options are plain integers, enums and pointers, for which all zeroes is a
+    // valid value, and `lzma_lzma_preset` only writes to them.
+    let mut options: lzma_options_lzma = unsafe { mem::zeroed() };
+    if unsafe { liblzma_sys::lzma_lzma_preset(&mut options, preset) } != 0 {
+        return Err(LzipError::StreamError(stream::Error::Options));
+    }
+
+    Ok(options)
+}
+
+/// Runs a liblzma memory usage function on a filter chain of a single LZMA1 filter.
+fn raw_memusage(
+    options: &mut lzma_options_lzma,
+    memusage: impl FnOnce(*const lzma_filter) -> u64,
+) -> Result<u64, LzipError> {
+    let filters = [
+        lzma_filter {
+            id: LZMA_FILTER_LZMA1,
+            options: ptr::from_mut(options).cast(),
+        },
+        lzma_filter {
+            id: LZMA_VLI_UNKNOWN,
+            options: ptr::null_mut(),
+        },
+    ];
+
+    // liblzma reports invalid options as `UINT64_MAX`.
+    match memusage(filters.as_ptr()) {
+        u64::MAX => Err(LzipError::StreamError(stream::Error::Options)),
+        usage => Ok(usage),
+    }
+}
+
+/// Runs all of `data` through `stream` and returns the output.
+fn process_all(mut stream: Stream, data: &[u8]) -> Result<Vec<u8>, LzipError> {
+    let mut output = Vec::new();
+    let mut output_buf = [0u8; 4096];
+    let mut pos = 0;
+
+    loop {
+        let eof = pos == data.len();
+
+        let before_out = stream.total_out();
+        let before_in = stream.total_in();
+        let status = stream.process(
+            &data[pos..],
+            &mut output_buf,
+            if eof { Action::Finish } else { Action::Run },
+        )?;
+        pos += processed(before_in, stream.total_in(), data.len() - pos)?;
+        let written = processed(before_out, stream.total_out(), output_buf.len())?;
+
+        output.extend_from_slice(&output_buf[..written]);
+
+        if status == Status::StreamEnd {
+            break;
+        }
+
+        if eof && written == 0 {
+            return Err(LzipError::UnexpectedEndOfStream);
+        }
+    }
+
+    Ok(output)
+}
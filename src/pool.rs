@@ -0,0 +1,81 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! A pool of byte buffers shared between encoders and decoders.
+
+use std::sync::{Arc, Mutex};
+
+/// The maximum number of idle buffers a pool keeps.
+const MAX_IDLE_BUFFERS: usize = 64;
+
+/// A pool of reusable byte buffers.
+///
+/// [`Encoder`](crate::Encoder)s and [`Decoder`](crate::Decoder)s given the same pool through
+/// [`EncoderOptions::buffer_pool`](crate::EncoderOptions::buffer_pool) and
+/// [`DecodeOptions::buffer_pool`](crate::DecodeOptions::buffer_pool) borrow their working
+/// buffers from it and hand them back once done, so a pipeline processing many small messages
+/// doesn't allocate fresh buffers for every one of them. Cloning the pool shares it.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::BufferPool;
+///
+/// let pool = BufferPool::new(4096);
+/// let buffer = pool.take();
+/// assert!(buffer.capacity() >= 4096);
+/// pool.give(buffer);
+/// assert_eq!(pool.idle(), 1);
+/// ```
+#[derive(Clone, Debug)]
+pub struct BufferPool {
+    /// The capacity newly allocated buffers are created with.
+    buffer_size: usize,
+    /// The buffers currently not in use.
+    buffers: Arc<Mutex<Vec<Vec<u8>>>>,
+}
+
+impl BufferPool {
+    /// Creates a new, empty `BufferPool` allocating buffers of `buffer_size` bytes.
+    pub fn new(buffer_size: usize) -> Self {
+        BufferPool {
+            buffer_size,
+            buffers: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// Takes an empty buffer from the pool, allocating a new one if none is idle.
+    pub fn take(&self) -> Vec<u8> {
+        self.lock()
+            .pop()
+            .unwrap_or_else(|| Vec::with_capacity(self.buffer_size))
+    }
+
+    /// Gives a buffer back to the pool for reuse.
+    ///
+    /// The buffer is cleared. Buffers without any capacity, or beyond the number of idle
+    /// buffers the pool keeps, are dropped instead.
+    pub fn give(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() == 0 {
+            return;
+        }
+
+        let mut buffers = self.lock();
+        if buffers.len() < MAX_IDLE_BUFFERS {
+            buffer.clear();
+            buffers.push(buffer);
+        }
+    }
+
+    /// Returns the number of idle buffers in the pool.
+    pub fn idle(&self) -> usize {
+        self.lock().len()
+    }
+
+    /// Locks the idle buffers, recovering them if another thread panicked while holding them.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<Vec<u8>>> {
+        self.buffers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
@@ -0,0 +1,65 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! The output targets decompressed data is written to.
+
+use std::io::Write;
+
+use crate::LzipError;
+
+/// A target receiving decompressed data from [`Decoder::decode`](crate::Decoder::decode).
+///
+/// Every [`Write`] implementation is a sink already, so files, `Vec`s and sockets can be
+/// passed as they are. Implementing this trait directly suits targets which aren't a byte
+/// stream, e.g. one only hashing or counting the data, or one framing every chunk for the
+/// network, without the ceremony of `io::Write` and its error type.
+///
+/// # Example
+///
+/// ```rust
+/// use lzipper::{Decoder, Encoder, LzipError, OutputSink};
+///
+/// struct CountingSink(u64);
+///
+/// impl OutputSink for CountingSink {
+///     fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), LzipError> {
+///         self.0 += chunk.len() as u64;
+///         Ok(())
+///     }
+/// }
+///
+/// let input = b"the quick brown fox jumps over the lazy dog";
+/// let encoded = Encoder::new(input.as_slice())
+///     .finish_to_vec()
+///     .expect("failed to encode");
+///
+/// let mut sink = CountingSink(0);
+/// let mut decoder = Decoder::new(encoded.as_slice());
+/// decoder.decode(&mut sink).expect("failed to decode");
+/// assert_eq!(sink.0, 43);
+/// ```
+pub trait OutputSink {
+    /// Takes the next chunk of decompressed data, in order.
+    ///
+    /// An error aborts decoding and is returned from it as is.
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), LzipError>;
+
+    /// Makes the chunks taken so far visible to whatever consumes the sink.
+    ///
+    /// Called once decoding has finished and every trailer has been verified, and after every
+    /// chunk with [`DecodeOptions::flush_each_chunk`](crate::DecodeOptions::flush_each_chunk).
+    /// Does nothing by default. An error fails decoding and is returned from it as is.
+    fn flush_output(&mut self) -> Result<(), LzipError> {
+        Ok(())
+    }
+}
+
+impl<W: Write + ?Sized> OutputSink for W {
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), LzipError> {
+        Ok(self.write_all(chunk)?)
+    }
+
+    fn flush_output(&mut self) -> Result<(), LzipError> {
+        Ok(self.flush()?)
+    }
+}
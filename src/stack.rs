@@ -0,0 +1,264 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Encoders and decoders working through fixed-size buffers instead of heap allocations.
+//!
+//! [`Encoder`](crate::Encoder) and [`Decoder`](crate::Decoder) read their input through a
+//! heap-allocated [`BufReader`](std::io::BufReader). The types here use arrays of a size chosen
+//! at compile time instead, which live wherever the encoder or decoder itself lives, e.g. on
+//! the stack. This suits embedded targets with little or no heap. liblzma still allocates its
+//! own state and dictionary through the C allocator, so the dictionary size bounds the memory
+//! used either way.
+//!
+//! # Example
+//!
+//! ```rust
+//! use lzipper::{StackDecoder, StackEncoder};
+//!
+//! let input = b"the quick brown fox jumps over the lazy dog";
+//!
+//! let mut encoded: Vec<u8> = Vec::new();
+//! let mut encoder: StackEncoder<_, 1024> = StackEncoder::new(input.as_slice());
+//! encoder.encode(&mut encoded).expect("failed to encode");
+//!
+//! let mut decoded: Vec<u8> = Vec::new();
+//! let mut decoder: StackDecoder<_, 1024> = StackDecoder::new(encoded.as_slice());
+//! decoder.decode(&mut decoded).expect("failed to decode");
+//!
+//! assert_eq!(input, decoded.as_slice());
+//! ```
+
+use std::io::{self, Read, Write};
+
+use crc32fast::Hasher;
+use liblzma::stream::{Action, Status};
+
+use crate::decoder::{CONCATENATED, IGNORE_CHECK};
+use crate::encoder::level_for_dict_size;
+use crate::format::{Header, Trailer, member_size};
+use crate::lzma1::{new_raw_decoder, new_raw_encoder};
+use crate::{DecodeOptions, DecodeStats, EncoderOptions, LzipError, processed};
+
+/// An encoder compressing a single member through two `BUF` byte arrays.
+///
+/// Only the compression level, dictionary size, LZMA parameters and cancellation token of the
+/// options are used; the whole input always becomes a single member.
+pub struct StackEncoder<R: Read, const BUF: usize> {
+    /// The input data stream.
+    input: R,
+    /// The options used for compression.
+    options: EncoderOptions,
+}
+
+impl<R: Read, const BUF: usize> StackEncoder<R, BUF> {
+    /// Creates a new `StackEncoder` instance with the default options.
+    pub fn new(input: R) -> Self {
+        Self::new_with_options(input, EncoderOptions::default())
+    }
+
+    /// Creates a new `StackEncoder` instance.
+    ///
+    /// `BUF` must not be zero, which fails to compile.
+    pub fn new_with_options(input: R, options: EncoderOptions) -> Self {
+        const { assert!(BUF > 0, "the buffer size must not be zero") };
+
+        StackEncoder { input, options }
+    }
+
+    /// Compresses the data from the input stream and writes it to the output stream.
+    pub fn encode<W: Write>(&mut self, output: &mut W) -> Result<(), LzipError> {
+        let resolved = self.options.resolve()?;
+        output.write_all(&Header::new(resolved.dict_size).to_bytes()?)?;
+
+        let mut stream = new_raw_encoder(&resolved)?;
+        let mut hasher = Hasher::new();
+
+        let mut input_buf = [0u8; BUF];
+        let mut output_buf = [0u8; BUF];
+        let (mut start, mut end) = (0, 0);
+        let mut eof = false;
+
+        loop {
+            if self.options.is_cancelled() {
+                return Err(LzipError::Cancelled);
+            }
+
+            if start == end && !eof {
+                start = 0;
+                end = read_some(&mut self.input, &mut input_buf)?;
+                eof = end == 0;
+            }
+
+            let before_out = stream.total_out();
+            let before_in = stream.total_in();
+            stream.process(
+                &input_buf[start..end],
+                &mut output_buf,
+                if eof { Action::Finish } else { Action::Run },
+            )?;
+            let read = processed(before_in, stream.total_in(), end - start)?;
+            let written = processed(before_out, stream.total_out(), output_buf.len())?;
+
+            hasher.update(&input_buf[start..start + read]);
+            start += read;
+            output.write_all(&output_buf[..written])?;
+
+            if eof && written == 0 {
+                break;
+            }
+        }
+
+        let trailer = Trailer::new(hasher.finalize(), stream.total_in(), stream.total_out());
+        output.write_all(&trailer.to_bytes())?;
+
+        Ok(())
+    }
+}
+
+/// A decoder decompressing through two `BUF` byte arrays.
+///
+/// Only the [`CONCATENATED`] and [`IGNORE_CHECK`] flags and the cancellation token of the
+/// options are used. Members failing verification always abort decoding.
+pub struct StackDecoder<R: Read, const BUF: usize> {
+    /// The compressed data input stream.
+    input: R,
+    /// The options used for decompression.
+    options: DecodeOptions,
+    /// The compressed data read ahead from the input.
+    buffer: [u8; BUF],
+    /// The position of the first unconsumed byte in `buffer`.
+    pos: usize,
+    /// The number of bytes read into `buffer`.
+    filled: usize,
+}
+
+impl<R: Read, const BUF: usize> StackDecoder<R, BUF> {
+    /// Creates a new `StackDecoder` instance with the default options.
+    pub fn new(input: R) -> Self {
+        Self::new_with_options(input, DecodeOptions::default())
+    }
+
+    /// Creates a new `StackDecoder` instance.
+    ///
+    /// `BUF` must not be zero, which fails to compile.
+    pub fn new_with_options(input: R, options: DecodeOptions) -> Self {
+        const { assert!(BUF > 0, "the buffer size must not be zero") };
+
+        StackDecoder {
+            input,
+            options,
+            buffer: [0; BUF],
+            pos: 0,
+            filled: 0,
+        }
+    }
+
+    /// Decompresses the data from the input stream and writes it to the output stream.
+    pub fn decode<W: Write>(&mut self, output: &mut W) -> Result<DecodeStats, LzipError> {
+        let mut stats = DecodeStats::default();
+        let mut output_buf = [0u8; BUF];
+
+        loop {
+            let mut header = [0; Header::SIZE];
+            self.read_exact(&mut header)?;
+            let dict_size = Header::from_bytes(&header)?.dict_size;
+
+            stats.members += 1;
+            let level = level_for_dict_size(dict_size);
+            if stats.members == 1 || stats.inferred_level == level {
+                stats.inferred_level = level;
+            } else {
+                stats.inferred_level = None;
+            }
+
+            let mut stream = new_raw_decoder(dict_size, None)?;
+            let mut hasher = Hasher::new();
+
+            loop {
+                if self.options.is_cancelled() {
+                    return Err(LzipError::Cancelled);
+                }
+
+                let eof = self.fill()? == 0;
+                let input_buf = &self.buffer[self.pos..self.filled];
+
+                let before_out = stream.total_out();
+                let before_in = stream.total_in();
+                let status = stream.process(
+                    input_buf,
+                    &mut output_buf,
+                    if eof { Action::Finish } else { Action::Run },
+                )?;
+                let read = processed(before_in, stream.total_in(), input_buf.len())?;
+                let written = processed(before_out, stream.total_out(), output_buf.len())?;
+
+                self.pos += read;
+                hasher.update(&output_buf[..written]);
+                output.write_all(&output_buf[..written])?;
+
+                if status == Status::StreamEnd {
+                    break;
+                }
+                if eof && written == 0 {
+                    return Err(LzipError::UnexpectedEndOfStream);
+                }
+            }
+
+            let mut trailer = [0; Trailer::SIZE];
+            self.read_exact(&mut trailer)?;
+            let trailer = Trailer::from_bytes(&trailer);
+
+            if trailer.crc32 != hasher.finalize() && !self.options.has_flag(IGNORE_CHECK) {
+                return Err(LzipError::InvalidCrc);
+            }
+            if trailer.uncompressed_size != stream.total_out() {
+                return Err(LzipError::InvalidDataSize);
+            }
+            if trailer.member_size != member_size(stream.total_in()) {
+                return Err(LzipError::InvalidMemberSize);
+            }
+
+            if !self.options.has_flag(CONCATENATED) || self.fill()? == 0 {
+                return Ok(stats);
+            }
+        }
+    }
+
+    /// Reads more input into the buffer once it is used up, returning the unconsumed length.
+    fn fill(&mut self) -> Result<usize, LzipError> {
+        if self.pos == self.filled {
+            self.pos = 0;
+            self.filled = read_some(&mut self.input, &mut self.buffer)?;
+        }
+
+        Ok(self.filled - self.pos)
+    }
+
+    /// Fills `buf` from the buffered input.
+    fn read_exact(&mut self, buf: &mut [u8]) -> Result<(), LzipError> {
+        let mut copied = 0;
+        while copied < buf.len() {
+            let available = self.fill()?;
+            if available == 0 {
+                return Err(LzipError::UnexpectedEndOfStream);
+            }
+
+            let n = available.min(buf.len() - copied);
+            buf[copied..copied + n].copy_from_slice(&self.buffer[self.pos..self.pos + n]);
+            self.pos += n;
+            copied += n;
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads from `input` into `buf`, retrying reads that were interrupted.
+fn read_some<R: Read>(input: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match input.read(buf) {
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            result => return result,
+        }
+    }
+}
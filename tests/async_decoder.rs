@@ -0,0 +1,59 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+#![cfg(feature = "tokio")]
+
+use lzipper::{AsyncDecoder, Encoder, LzipError};
+
+use std::time::Duration;
+
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn decode_to_sender() {
+    let input = vec![0; 10 * 1024 * 1024]; // 10 MiB of zeros
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let (tx, mut rx) = mpsc::channel(1);
+    let decoder = AsyncDecoder::new(std::io::Cursor::new(encoded));
+    let handle = tokio::spawn(decoder.decode_to_sender(tx));
+
+    let mut decoded: Vec<u8> = Vec::new();
+    while let Some(chunk) = rx.recv().await {
+        decoded.extend_from_slice(&chunk.expect("failed to decode"));
+        if decoded.len() < 64 * 1024 {
+            tokio::time::sleep(Duration::from_millis(1)).await; // slow consumer
+        }
+    }
+
+    handle
+        .await
+        .expect("decode task panicked")
+        .expect("failed to decode");
+    assert_eq!(input, decoded);
+}
+
+#[tokio::test]
+async fn decode_to_sender_error() {
+    let corrupt_data = b"LZIP\x01\x0c\x00\x34\x19\x49\xee\x8d\xdd\x3d\x3a\xdf\xff\xff\xdd\x12\x00\x00\x00\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00\x00\x2a\x00\x00\x00\x00\x00\x00\x00";
+
+    let (tx, mut rx) = mpsc::channel(1);
+    let decoder = AsyncDecoder::new(corrupt_data.as_slice());
+    let handle = tokio::spawn(decoder.decode_to_sender(tx));
+
+    let mut items = Vec::new();
+    while let Some(item) = rx.recv().await {
+        items.push(item);
+    }
+
+    let error = LzipError::Corrupt {
+        member: 0,
+        offset: 22,
+        error: Box::new(LzipError::InvalidCrc),
+    };
+    assert_eq!(items.last(), Some(&Err(error.clone())));
+    assert_eq!(handle.await.expect("decode task panicked"), Err(error));
+}
@@ -0,0 +1,32 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+#![cfg(feature = "bytes")]
+
+use lzipper::decoder::CONCATENATED;
+use lzipper::{DecodeOptions, Decoder, Encoder};
+
+#[test]
+fn decode_bytes() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(10_000);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    for half in input.chunks(input.len() / 2) {
+        let mut encoder = Encoder::new(half);
+        encoder.encode(&mut encoded).expect("failed to encode");
+    }
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    let mut chunks = Vec::new();
+    let stats = decoder
+        .decode_bytes(|chunk| {
+            chunks.push(chunk);
+            Ok(())
+        })
+        .expect("failed to decode");
+
+    assert!(chunks.len() > 1);
+    assert_eq!(chunks.concat(), input);
+    assert_eq!(stats.members, 2);
+}
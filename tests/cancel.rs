@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::{DecodeOptions, Decoder, Encoder, EncoderOptions, LzipError};
+
+use std::{
+    io::{self, Read},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+/// A reader which sets the cancel token as soon as it is first read from.
+struct CancellingReader<R: Read> {
+    inner: R,
+    token: Arc<AtomicBool>,
+}
+
+impl<R: Read> Read for CancellingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.token.store(true, Ordering::Relaxed);
+        self.inner.read(buf)
+    }
+}
+
+#[test]
+fn cancel_encode() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+    let token = Arc::new(AtomicBool::new(false));
+
+    let reader = CancellingReader {
+        inner: input.as_slice(),
+        token: token.clone(),
+    };
+    let options = EncoderOptions::default().cancel_token(token);
+    let mut encoder = Encoder::new_with_options(reader, options);
+    let result = encoder.encode(&mut Vec::new());
+
+    assert_eq!(result.unwrap_err(), LzipError::Cancelled);
+}
+
+#[test]
+fn cancel_decode() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let token = Arc::new(AtomicBool::new(false));
+    let reader = CancellingReader {
+        inner: encoded.as_slice(),
+        token: token.clone(),
+    };
+    let options = DecodeOptions::default().cancel_token(token);
+    let mut decoder = Decoder::new_with_options(reader, options);
+    let mut decoded = Vec::new();
+    let result = decoder.decode(&mut decoded);
+
+    assert_eq!(result.unwrap_err(), LzipError::Cancelled);
+    assert!(decoded.len() < input.len());
+}
+
+#[test]
+fn time_budget_exceeded() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+
+    let mut encoder = Encoder::new(input.as_slice());
+    let result = encoder.encode_adaptive(&mut Vec::new(), Duration::ZERO);
+    assert_eq!(result.unwrap_err(), LzipError::TimeBudgetExceeded);
+
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder
+        .encode_adaptive(&mut encoded, Duration::from_secs(3600))
+        .expect("failed to encode");
+
+    let mut decoded = Vec::new();
+    let mut decoder = Decoder::new(encoded.as_slice());
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(input, decoded);
+}
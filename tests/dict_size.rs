@@ -0,0 +1,361 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use liblzma::stream;
+use lzipper::{
+    CompressionLevel, DecodeOptions, Decoder, Encoder, EncoderOptions, Header, LzipError,
+    MemoryBudget, decode_dict_size, decoder_memory_usage, encode_dict_size, encoder_memory_usage,
+    min_dict_size_for,
+};
+
+use std::io::Read;
+
+#[test]
+fn dict_size_bytes() {
+    for byte in 0..=u8::MAX {
+        let Ok(dict_size) = decode_dict_size(byte) else {
+            continue;
+        };
+
+        let encoded = encode_dict_size(dict_size).expect("failed to encode dict size");
+        assert_eq!(decode_dict_size(encoded), Ok(dict_size));
+    }
+}
+
+#[test]
+fn dict_size_rounding() {
+    for dict_size in [
+        1 << 12,
+        (1 << 12) + 1,
+        65535,
+        1 << 20,
+        3 << 20,
+        (1 << 29) - 1,
+        1 << 29,
+    ] {
+        let encoded = encode_dict_size(dict_size).expect("failed to encode dict size");
+        let decoded = decode_dict_size(encoded).expect("failed to decode dict size");
+
+        assert!(decoded >= dict_size);
+        assert!(decoded - dict_size < decoded / 8);
+    }
+}
+
+#[test]
+fn invalid_dict_sizes() {
+    for dict_size in [0, 1, (1 << 12) - 1, (1 << 29) + 1, u32::MAX] {
+        assert_eq!(encode_dict_size(dict_size), Err(LzipError::InvalidDictSize));
+    }
+
+    for byte in [0x00, 0x0B, 0x1E, 0x1F] {
+        assert_eq!(decode_dict_size(byte), Err(LzipError::InvalidDictSize));
+    }
+}
+
+#[test]
+fn dict_size_exponent_out_of_range() {
+    // Exponents 30 and 31 are rejected with any fraction bits, instead of wrapping around.
+    for fraction in 0..8u8 {
+        for exponent in [30, 31] {
+            let byte = (fraction << 5) | exponent;
+            assert_eq!(decode_dict_size(byte), Err(LzipError::InvalidDictSize));
+        }
+    }
+
+    assert_eq!(decode_dict_size(0x1D), Ok(1 << 29));
+    assert_eq!(decode_dict_size(0xFD), Ok((1 << 29) - 7 * (1 << 25)));
+}
+
+#[test]
+fn max_dict_size_boundary() {
+    // No byte decodes to a size above 512 MiB, whatever its exponent and fraction bits.
+    for byte in 0..=u8::MAX {
+        if let Ok(dict_size) = decode_dict_size(byte) {
+            assert!(dict_size <= 1 << 29);
+        }
+    }
+
+    // Exactly 512 MiB is accepted, and decodes.
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let mut encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+    encoded[5] = 0x1D;
+
+    let header: [u8; Header::SIZE] = encoded[..Header::SIZE].try_into().unwrap();
+    let header = Header::from_bytes(&header).expect("failed to parse header");
+    assert_eq!(header.dict_size, 1 << 29);
+
+    let mut decoder = Decoder::new(encoded.as_slice());
+    assert_eq!(decoder.validate_header_only(), Ok(1 << 29));
+    let mut decoded: Vec<u8> = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+    drop(decoder);
+    assert_eq!(decoded, input);
+
+    // The smallest size above it the header can express is 2^30 less 7/16, i.e. 576 MiB.
+    encoded[5] = 0xFE;
+    let header: [u8; Header::SIZE] = encoded[..Header::SIZE].try_into().unwrap();
+    assert_eq!(Header::from_bytes(&header), Err(LzipError::InvalidDictSize));
+
+    let mut decoder = Decoder::new(encoded.as_slice());
+    assert_eq!(
+        decoder.validate_header_only(),
+        Err(LzipError::InvalidDictSize)
+    );
+    let mut decoder = Decoder::new(encoded.as_slice());
+    assert_eq!(
+        decoder.decode(&mut Vec::new()),
+        Err(LzipError::InvalidDictSize)
+    );
+}
+
+#[test]
+fn min_dict_sizes() {
+    // Clamped to the minimum of 4 KiB.
+    assert_eq!(min_dict_size_for(0), 1 << 12);
+    assert_eq!(min_dict_size_for(1), 1 << 12);
+    assert_eq!(min_dict_size_for(1 << 12), 1 << 12);
+
+    // Rounded up to the next power of two minus a multiple of a sixteenth of it.
+    assert_eq!(min_dict_size_for((1 << 12) + 1), (1 << 12) + (1 << 9));
+    assert_eq!(min_dict_size_for(5000), 5 << 10);
+    assert_eq!(min_dict_size_for((1 << 20) - 1), 1 << 20);
+    assert_eq!(min_dict_size_for(1 << 20), 1 << 20);
+    assert_eq!(min_dict_size_for((1 << 20) + 1), (1 << 20) + (1 << 17));
+    assert_eq!(min_dict_size_for((3 << 19) + 1), 13 << 17);
+
+    // Clamped to the maximum of 512 MiB.
+    assert_eq!(min_dict_size_for(1 << 29), 1 << 29);
+    assert_eq!(min_dict_size_for((1 << 29) + 1), 1 << 29);
+    assert_eq!(min_dict_size_for(u64::MAX), 1 << 29);
+
+    for size in [0, 1000, 1 << 16, 3 << 20, u64::MAX] {
+        let dict_size = min_dict_size_for(size);
+        let encoded = encode_dict_size(dict_size).expect("failed to encode dict size");
+        assert_eq!(decode_dict_size(encoded), Ok(dict_size));
+    }
+}
+
+#[test]
+fn resolve_levels() {
+    for (level, dict_size, nice_len) in [
+        (CompressionLevel::Fastest, 1 << 18, 128),
+        (CompressionLevel::Fast, 1 << 22, 273),
+        (CompressionLevel::Default, 1 << 23, 64),
+        (CompressionLevel::Maximum, 1 << 26, 64),
+    ] {
+        let resolved = EncoderOptions::default()
+            .level(level)
+            .resolve()
+            .expect("failed to resolve options");
+
+        assert_eq!(resolved.level, level);
+        assert_eq!(resolved.preset, level as u32);
+        assert_eq!(resolved.dict_size, dict_size);
+        assert_eq!((resolved.lc, resolved.lp, resolved.pb), (3, 0, 2));
+        assert_eq!(resolved.nice_len, nice_len);
+        assert_eq!(resolved.block_size, None);
+        assert_eq!(resolved.threads, 1);
+    }
+}
+
+#[test]
+fn resolve_overrides() {
+    let resolved = EncoderOptions::default()
+        .dict_size(1 << 20)
+        .lzma_params(0, 2, 2)
+        .threads(4)
+        .resolve()
+        .expect("failed to resolve options");
+    assert_eq!(resolved.dict_size, 1 << 20);
+    assert_eq!((resolved.lc, resolved.lp, resolved.pb), (0, 2, 2));
+    assert_eq!(resolved.block_size, Some(2 << 20));
+    assert_eq!(resolved.threads, 4);
+
+    let resolved = EncoderOptions::default()
+        .auto_dict(true)
+        .input_size(1000)
+        .block_size(1 << 16)
+        .resolve()
+        .expect("failed to resolve options");
+    assert_eq!(resolved.dict_size, 1 << 12);
+    assert_eq!(resolved.block_size, Some(1 << 16));
+
+    assert_eq!(
+        EncoderOptions::default().dict_size(1 << 30).resolve(),
+        Err(LzipError::InvalidDictSize)
+    );
+    assert_eq!(
+        EncoderOptions::default().lzma_params(3, 2, 2).resolve(),
+        Err(LzipError::StreamError(stream::Error::Options))
+    );
+}
+
+#[test]
+fn auto_dict_small_inputs() {
+    let value = [0x5A; 500];
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Fastest)
+        .auto_dict(true)
+        .input_size(value.len() as u64);
+    let encoded = Encoder::new_with_options(value.as_slice(), options)
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let header: [u8; Header::SIZE] = encoded[..Header::SIZE].try_into().unwrap();
+    let header = Header::from_bytes(&header).expect("failed to parse header");
+    assert_eq!(header.dict_size, 1 << 12);
+
+    // Between powers of two, the smallest size the header can declare is used.
+    for (input_size, dict_size) in [
+        (1 << 12, 1 << 12),
+        ((1 << 12) + 1, 4608),
+        (5000, 5 << 10),
+        (12 << 10, 12 << 10),
+        (60 << 10, 60 << 10),
+        ((1 << 16) + 1, 72 << 10),
+    ] {
+        let resolved = EncoderOptions::default()
+            .auto_dict(true)
+            .input_size(input_size)
+            .resolve()
+            .expect("failed to resolve options");
+        assert_eq!(resolved.dict_size, dict_size);
+        assert_eq!(min_dict_size_for(input_size), dict_size);
+    }
+}
+
+#[test]
+fn memory_usage() {
+    let mut last = 0;
+    for level in [
+        CompressionLevel::Fastest,
+        CompressionLevel::Fast,
+        CompressionLevel::Default,
+        CompressionLevel::Maximum,
+    ] {
+        let options = EncoderOptions::default().level(level);
+        let dict_size = options
+            .resolve()
+            .expect("failed to resolve options")
+            .dict_size;
+
+        let encoder = encoder_memory_usage(&options).expect("failed to estimate memory");
+        let decoder = decoder_memory_usage(dict_size).expect("failed to estimate memory");
+        assert!(encoder > last);
+        assert!(encoder > decoder);
+        assert!(decoder >= dict_size as u64);
+        assert!(decoder < dict_size as u64 + (1 << 20));
+        last = encoder;
+
+        let threaded = encoder_memory_usage(&options.threads(4)).expect("failed to estimate");
+        assert_eq!(threaded, 4 * encoder);
+    }
+
+    assert_eq!(
+        encoder_memory_usage(&EncoderOptions::default().dict_size(1 << 30)),
+        Err(LzipError::InvalidDictSize)
+    );
+    assert_eq!(
+        decoder_memory_usage(1 << 30),
+        Err(LzipError::InvalidDictSize)
+    );
+}
+
+#[test]
+fn memory_budget() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let mut encoded = Encoder::new_with_level(input.as_slice(), CompressionLevel::Fastest)
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    // A header declaring 512 MiB is rejected before the dictionary is allocated.
+    let budget = MemoryBudget::new(1 << 20);
+    let mut huge = encoded.clone();
+    huge[5] = 0x1D;
+    let options = DecodeOptions::default().memory_budget(budget.clone());
+    let mut decoder = Decoder::new_with_options(huge.as_slice(), options);
+    assert_eq!(
+        decoder.decode(&mut Vec::new()),
+        Err(LzipError::MemoryLimitExceeded)
+    );
+    assert_eq!(budget.used(), 0);
+
+    // The 256 KiB dictionary of the fastest level fits, and is given back afterwards.
+    let options = DecodeOptions::default().memory_budget(budget.clone());
+    let mut decoded: Vec<u8> = Vec::new();
+    Decoder::new_with_options(encoded.as_slice(), options)
+        .decode(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(decoded, input);
+    assert_eq!(budget.used(), 0);
+
+    // The budget is shared: what one decoder holds isn't available to another.
+    encoded[5] = encode_dict_size(768 << 10).expect("failed to encode dict size");
+    let options = DecodeOptions::default().memory_budget(budget.clone());
+    let mut first = Decoder::new_with_options(encoded.as_slice(), options.clone());
+    let mut buf = [0; 4];
+    first.read_exact(&mut buf).expect("failed to read");
+    assert!(budget.used() > 768 << 10);
+
+    let mut second = Decoder::new_with_options(encoded.as_slice(), options);
+    assert_eq!(
+        second.decode(&mut Vec::new()),
+        Err(LzipError::MemoryLimitExceeded)
+    );
+    drop(first);
+    assert_eq!(budget.used(), 0);
+
+    // Compressing reserves the match finder along with the dictionary.
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Fastest)
+        .memory_budget(budget.clone());
+    assert_eq!(
+        Encoder::new_with_options(input.as_slice(), options).finish_to_vec(),
+        Err(LzipError::MemoryLimitExceeded)
+    );
+
+    let budget = MemoryBudget::new(16 << 20);
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Fastest)
+        .memory_budget(budget.clone());
+    let reencoded = Encoder::new_with_options(input.as_slice(), options)
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(
+        reencoded,
+        Encoder::new_with_level(input.as_slice(), CompressionLevel::Fastest)
+            .finish_to_vec()
+            .expect("failed to encode")
+    );
+    assert_eq!(budget.used(), 0);
+}
+
+#[test]
+fn roundtrip_preset_and_dict() {
+    let input: Vec<u8> = (0..200_000u32).map(|i| (i * 7 % 251) as u8).collect();
+
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new_with_preset_and_dict(input.as_slice(), 9, 1 << 20)
+        .expect("failed to create encoder");
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let header: [u8; Header::SIZE] = encoded[..Header::SIZE].try_into().unwrap();
+    let header = Header::from_bytes(&header).expect("failed to parse header");
+    assert_eq!(header.dict_size, 1 << 20);
+
+    let mut decoded = Vec::new();
+    let mut decoder = Decoder::new(encoded.as_slice());
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, input);
+
+    assert!(matches!(
+        Encoder::new_with_preset_and_dict(input.as_slice(), 10, 1 << 20),
+        Err(LzipError::StreamError(stream::Error::Options))
+    ));
+    assert!(matches!(
+        Encoder::new_with_preset_and_dict(input.as_slice(), 9, 1 << 30),
+        Err(LzipError::InvalidDictSize)
+    ));
+}
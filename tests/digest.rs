@@ -0,0 +1,31 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+#![cfg(feature = "digest")]
+
+use lzipper::decoder::CONCATENATED;
+use lzipper::{DecodeOptions, Decoder, Encoder};
+
+use sha2::{Digest, Sha256};
+
+#[test]
+fn decode_verified() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(1000);
+
+    let mut encoded = Vec::new();
+    for half in input.chunks(input.len() / 2) {
+        let mut encoder = Encoder::new(half);
+        encoder.encode(&mut encoded).expect("failed to encode");
+    }
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoded = Vec::new();
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    let (crc32, digest) = decoder
+        .decode_verified(&mut decoded, Sha256::new())
+        .expect("failed to decode");
+
+    assert_eq!(decoded, input);
+    assert_eq!(crc32, crc32fast::hash(&input));
+    assert_eq!(digest, Sha256::digest(&input).to_vec());
+}
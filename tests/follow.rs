@@ -0,0 +1,74 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::decoder::CONCATENATED;
+use lzipper::{DecodeOptions, Decoder, Encoder, LzipError};
+
+use std::{
+    fs::File,
+    io::{Read, Write},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+#[test]
+fn follow_growing_file() {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let log_path = std::env::temp_dir().join(format!("test_follow_{}.lz", timestamp));
+
+    let members: Vec<Vec<u8>> = [
+        b"the quick brown fox ".as_slice(),
+        b"jumps over the lazy dog",
+    ]
+    .iter()
+    .map(|member| {
+        Encoder::new(*member)
+            .finish_to_vec()
+            .expect("failed to encode")
+    })
+    .collect();
+
+    let mut log = File::create(&log_path).expect("failed to create log file");
+    let reader = File::open(&log_path).expect("failed to open log file for reading");
+
+    // Append the members in pieces, so the follower runs out of input mid-header, mid-data
+    // and mid-trailer.
+    let writer = thread::spawn(move || {
+        for member in members {
+            for piece in [&member[..3], &member[3..10], &member[10..member.len() - 5]] {
+                log.write_all(piece).expect("failed to write log");
+                thread::sleep(Duration::from_millis(20));
+            }
+            log.write_all(&member[member.len() - 5..])
+                .expect("failed to write log");
+        }
+    });
+
+    let token = Arc::new(AtomicBool::new(false));
+    let options = DecodeOptions::default()
+        .lzma_flags(CONCATENATED)
+        .follow(true)
+        .poll_interval(Duration::from_millis(5))
+        .cancel_token(Arc::clone(&token));
+    let mut decoder = Decoder::new_with_options(reader, options);
+
+    let mut decoded = vec![0; 43];
+    decoder.read_exact(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, b"the quick brown fox jumps over the lazy dog");
+    writer.join().expect("writer thread panicked");
+
+    // The follower keeps waiting for another member until it is cancelled.
+    token.store(true, Ordering::Relaxed);
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::Cancelled);
+    assert_eq!(decoder.stats().members, 2);
+
+    std::fs::remove_file(&log_path).expect("failed to remove log file");
+}
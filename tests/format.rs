@@ -0,0 +1,108 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::decoder::{CONCATENATED, IGNORE_CHECK};
+use lzipper::{DecodeOptions, Decoder, Encoder, Header, LzipError, Trailer};
+
+#[test]
+fn header_roundtrip() {
+    for dict_size in [1 << 12, 1 << 16, 3 << 20, 1 << 29] {
+        let bytes = Header::new(dict_size).to_bytes().expect("failed to encode");
+        assert_eq!(&bytes[..5], b"LZIP\x01");
+
+        let header = Header::from_bytes(&bytes).expect("failed to parse");
+        assert_eq!(header, Header::new(dict_size));
+    }
+}
+
+#[test]
+fn header_invalid() {
+    let bytes = Header {
+        version: 0,
+        dict_size: 1 << 20,
+    }
+    .to_bytes()
+    .expect("failed to encode");
+    assert_eq!(bytes[4], 0);
+    assert_eq!(
+        Header::from_bytes(&bytes).unwrap_err(),
+        LzipError::UnsupportedVersion
+    );
+
+    assert_eq!(
+        Header::new(1 << 30).to_bytes().unwrap_err(),
+        LzipError::InvalidDictSize
+    );
+    assert_eq!(
+        Header::from_bytes(b"LZMA\x01\x0c").unwrap_err(),
+        LzipError::InvalidMagic
+    );
+}
+
+#[test]
+fn trailer_roundtrip() {
+    let trailer = Trailer::new(0xdead_beef, u64::MAX, 1234);
+    assert_eq!(trailer.member_size, 6 + 1234 + 20);
+
+    let bytes = trailer.to_bytes();
+    assert_eq!(&bytes[..4], &[0xef, 0xbe, 0xad, 0xde]);
+    assert_eq!(Trailer::from_bytes(&bytes), trailer);
+}
+
+#[test]
+fn encoded_member() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let header =
+        Header::from_bytes(encoded[..Header::SIZE].try_into().unwrap()).expect("failed to parse");
+    assert_eq!(header.dict_size, 1 << 23);
+
+    let trailer = Trailer::from_bytes(encoded[encoded.len() - Trailer::SIZE..].try_into().unwrap());
+    assert_eq!(trailer.crc32, crc32fast::hash(input));
+    assert_eq!(trailer.uncompressed_size, input.len() as u64);
+    assert_eq!(trailer.member_size, encoded.len() as u64);
+}
+
+#[test]
+fn decoded_trailers() {
+    // "hello\n" with the CRC32 in the trailer zeroed.
+    let crafted = b"LZIP\x01\x0c\x00\x34\x19\x49\xee\x8d\xdd\x3d\x3a\xdf\xff\xff\xdd\x12\x00\x00\x00\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00\x00\x2a\x00\x00\x00\x00\x00\x00\x00";
+    let stored = Trailer {
+        crc32: 0,
+        uncompressed_size: 6,
+        member_size: 42,
+    };
+
+    let options = DecodeOptions::default().lzma_flags(IGNORE_CHECK);
+    let mut decoder = Decoder::new_with_options(crafted.as_slice(), options);
+    assert_eq!(decoder.last_trailer(), None);
+
+    let mut decoded = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, b"hello\n");
+    assert_eq!(decoder.last_trailer(), Some(stored));
+    assert_eq!(decoder.trailers(), &[stored]);
+    drop(decoder);
+
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let mut encoded = crafted.to_vec();
+    Encoder::new(input.as_slice())
+        .encode(&mut encoded)
+        .expect("failed to encode");
+    let member = Trailer {
+        crc32: crc32fast::hash(input),
+        uncompressed_size: input.len() as u64,
+        member_size: (encoded.len() - crafted.len()) as u64,
+    };
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED | IGNORE_CHECK);
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    let mut decoded = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoder.trailers(), &[stored, member]);
+    assert_eq!(decoder.last_trailer(), Some(member));
+}
@@ -0,0 +1,73 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::decoder::CONCATENATED;
+use lzipper::{DecodeOptions, DecodeProgress, Decoder, Encoder, EncoderOptions};
+
+#[test]
+fn push_frames() {
+    let input: Vec<u8> = (0..20_000u32)
+        .flat_map(|i| (i % 97).to_le_bytes())
+        .collect();
+    let options = EncoderOptions::default().block_size(32 * 1024);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    Encoder::new_with_options(input.as_slice(), options)
+        .encode(&mut encoded)
+        .expect("failed to encode");
+
+    let mut decoder = Decoder::new_framed(DecodeOptions::default().lzma_flags(CONCATENATED));
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut progress = Vec::new();
+    for frame in encoded.chunks(100) {
+        progress.push(
+            decoder
+                .push_frame(frame, &mut decoded)
+                .expect("failed to push frame"),
+        );
+    }
+
+    assert_eq!(decoded, input);
+    assert_eq!(decoder.stats().members, 3);
+    assert_eq!(progress.last(), Some(&DecodeProgress::MemberComplete));
+    assert!(
+        progress[..progress.len() - 1]
+            .iter()
+            .all(|progress| *progress != DecodeProgress::StreamEnd)
+    );
+
+    // Without CONCATENATED, the first member ends the stream.
+    let mut decoder = Decoder::new_framed(DecodeOptions::default());
+    let mut first: Vec<u8> = Vec::new();
+    let mut progress = DecodeProgress::NeedMore;
+    for frame in encoded.chunks(100) {
+        progress = decoder
+            .push_frame(frame, &mut first)
+            .expect("failed to push frame");
+    }
+    assert_eq!(progress, DecodeProgress::StreamEnd);
+    assert_eq!(first, input[..32 * 1024]);
+}
+
+#[test]
+fn push_truncated_frames() {
+    let mut encoded: Vec<u8> = Vec::new();
+    Encoder::new(b"the quick brown fox jumps over the lazy dog".as_slice())
+        .encode(&mut encoded)
+        .expect("failed to encode");
+
+    let mut decoder = Decoder::new_framed(DecodeOptions::default());
+    let mut decoded: Vec<u8> = Vec::new();
+    for frame in encoded[..encoded.len() - 1].chunks(7) {
+        let progress = decoder
+            .push_frame(frame, &mut decoded)
+            .expect("failed to push frame");
+        assert_eq!(progress, DecodeProgress::NeedMore);
+    }
+
+    let progress = decoder
+        .push_frame(&encoded[encoded.len() - 1..], &mut decoded)
+        .expect("failed to push frame");
+    assert_eq!(progress, DecodeProgress::StreamEnd);
+    assert_eq!(decoded, b"the quick brown fox jumps over the lazy dog");
+}
@@ -0,0 +1,173 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+#![cfg(feature = "fs")]
+
+use lzipper::fs::{AtomicWriter, compress_file, decompress_file};
+use lzipper::{DecodeOptions, Encoder, EncoderOptions, LzipError};
+
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Creates an empty directory for a test, unique to this run.
+fn temp_dir(name: &str) -> PathBuf {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+
+    let dir = std::env::temp_dir().join(format!("test_{}_{}", name, timestamp));
+    fs::create_dir_all(&dir).expect("failed to create temp dir");
+    dir
+}
+
+#[test]
+fn compress_file_keep_source() {
+    let dir = temp_dir("fs_keep");
+    let input_path = dir.join("notes.txt");
+    fs::write(&input_path, b"the quick brown fox jumps over the lazy dog")
+        .expect("failed to write input file");
+
+    let compressed = compress_file(&input_path, None, EncoderOptions::default(), true)
+        .expect("failed to compress");
+    assert_eq!(compressed, dir.join("notes.txt.lz"));
+    assert!(input_path.exists());
+
+    // The source is in the way of the decompressed file.
+    let result = decompress_file(&compressed, None, DecodeOptions::default(), true);
+    assert!(matches!(result, Err(LzipError::IoError(_))));
+    assert!(compressed.exists());
+
+    let decompressed_path = dir.join("copy.txt");
+    let decompressed = decompress_file(
+        &compressed,
+        Some(&decompressed_path),
+        DecodeOptions::default(),
+        true,
+    )
+    .expect("failed to decompress");
+    assert_eq!(decompressed, decompressed_path);
+    assert!(compressed.exists());
+    assert_eq!(
+        fs::read(&decompressed).expect("failed to read decompressed file"),
+        b"the quick brown fox jumps over the lazy dog"
+    );
+
+    fs::remove_dir_all(&dir).expect("failed to remove temp dir");
+}
+
+#[test]
+fn compress_file_delete_source() {
+    let dir = temp_dir("fs_delete");
+    let input_path = dir.join("archive.tar");
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(1000);
+    fs::write(&input_path, &input).expect("failed to write input file");
+
+    let compressed_path = dir.join("archive.tlz");
+    let compressed = compress_file(
+        &input_path,
+        Some(&compressed_path),
+        EncoderOptions::default(),
+        false,
+    )
+    .expect("failed to compress");
+    assert_eq!(compressed, compressed_path);
+    assert!(!input_path.exists());
+
+    let decompressed = decompress_file(&compressed, None, DecodeOptions::default(), false)
+        .expect("failed to decompress");
+    assert_eq!(decompressed, input_path);
+    assert!(!compressed.exists());
+    assert_eq!(
+        fs::read(&decompressed).expect("failed to read decompressed file"),
+        input
+    );
+
+    fs::remove_dir_all(&dir).expect("failed to remove temp dir");
+}
+
+#[test]
+fn decompress_file_corrupt() {
+    let dir = temp_dir("fs_corrupt");
+    let input_path = dir.join("notes.txt");
+    fs::write(&input_path, b"the quick brown fox jumps over the lazy dog")
+        .expect("failed to write input file");
+    let compressed = compress_file(&input_path, None, EncoderOptions::default(), false)
+        .expect("failed to compress");
+
+    let mut data = fs::read(&compressed).expect("failed to read compressed file");
+    let crc_offset = data.len() - 20;
+    data[crc_offset] ^= 0xFF;
+    fs::write(&compressed, &data).expect("failed to write compressed file");
+
+    // Neither is the source removed nor the incomplete output left behind.
+    let result = decompress_file(&compressed, None, DecodeOptions::default(), false);
+    assert!(matches!(result, Err(LzipError::Corrupt { .. })));
+    assert!(compressed.exists());
+    assert!(!input_path.exists());
+
+    fs::remove_dir_all(&dir).expect("failed to remove temp dir");
+}
+
+/// A writer failing once `limit` bytes have been written through it.
+struct FailingWriter<W> {
+    inner: W,
+    limit: usize,
+}
+
+impl<W: Write> Write for FailingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.len() > self.limit {
+            return Err(io::Error::other("disk full"));
+        }
+        self.limit -= buf.len();
+        self.inner.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[test]
+fn atomic_writer_discards_on_error() {
+    let dir = temp_dir("fs_atomic");
+    let dst = dir.join("data.lz");
+    let input: Vec<u8> = (0..100_000u32).flat_map(|i| i.to_le_bytes()).collect();
+
+    let writer = AtomicWriter::create(&dst).expect("failed to create writer");
+    let mut output = FailingWriter {
+        inner: writer,
+        limit: 1000,
+    };
+    let result = Encoder::new(input.as_slice()).encode(&mut output);
+    assert!(matches!(result, Err(LzipError::IoError(_))));
+    drop(output);
+
+    // Neither the destination nor the temporary file is left behind.
+    assert_eq!(
+        fs::read_dir(&dir).expect("failed to list temp dir").count(),
+        0
+    );
+
+    let mut output = AtomicWriter::create_new(&dst).expect("failed to create writer");
+    Encoder::new(input.as_slice())
+        .encode(&mut output)
+        .expect("failed to encode");
+    assert!(!dst.exists());
+    output.commit().expect("failed to commit");
+    assert!(dst.exists());
+    assert_eq!(
+        fs::read_dir(&dir).expect("failed to list temp dir").count(),
+        1
+    );
+
+    let result = AtomicWriter::create_new(&dst);
+    assert!(matches!(result, Err(LzipError::IoError(_))));
+
+    fs::remove_dir_all(&dir).expect("failed to remove temp dir");
+}
@@ -1,7 +1,18 @@
 // SPDX-License-Identifier: GPL-2.0-or-later
 // SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
 
-use lzipper::{Decoder, LzipError};
+use lzipper::decoder::{CONCATENATED, IGNORE_CHECK, MIN_RATIO_INPUT};
+use lzipper::{
+    CompressionLevel, DecodeOptions, DecodeWarning, Decoder, Encoder, LzipError, MemberErrorPolicy,
+    MemberInfo, TrailerFormat, split_members,
+};
+
+use std::error::Error;
+use std::io;
+use std::sync::atomic::AtomicBool;
+use std::sync::{Arc, mpsc};
+
+use liblzma::stream;
 
 #[test]
 fn invalid_magic() {
@@ -56,7 +67,14 @@ fn invalid_crc() {
     let result = decoder.decode(&mut Vec::new());
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), LzipError::InvalidCrc);
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 0,
+            offset: 22,
+            error: Box::new(LzipError::InvalidCrc),
+        }
+    );
 }
 
 #[test]
@@ -67,7 +85,14 @@ fn invalid_data_size() {
     let result = decoder.decode(&mut Vec::new());
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), LzipError::InvalidDataSize);
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 0,
+            offset: 22,
+            error: Box::new(LzipError::InvalidDataSize),
+        }
+    );
 }
 
 #[test]
@@ -78,5 +103,402 @@ fn invalid_member_size() {
     let result = decoder.decode(&mut Vec::new());
 
     assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), LzipError::InvalidMemberSize);
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 0,
+            offset: 22,
+            error: Box::new(LzipError::InvalidMemberSize),
+        }
+    );
+}
+
+#[test]
+fn invalid_header_only() {
+    let corrupt_data = b"LZIP\x01\x00";
+
+    let mut decoder = Decoder::new(corrupt_data.as_slice());
+    let result = decoder.validate_header_only();
+
+    assert!(result.is_err());
+    assert_eq!(result.unwrap_err(), LzipError::InvalidDictSize);
+}
+
+#[test]
+fn ignored_crc() {
+    let corrupt_data = b"LZIP\x01\x0c\x00\x34\x19\x49\xee\x8d\xdd\x3d\x3a\xdf\xff\xff\xdd\x12\x00\x00\x00\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00\x00\x2a\x00\x00\x00\x00\x00\x00\x00";
+
+    let options = DecodeOptions::default().lzma_flags(IGNORE_CHECK);
+    let mut decoder = Decoder::new_with_options(corrupt_data.as_slice(), options);
+    let mut decoded = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(decoded.as_slice(), b"hello\n");
+}
+
+#[test]
+fn structural_only() {
+    let corrupt_data = b"LZIP\x01\x0c\x00\x34\x19\x49\xee\x8d\xdd\x3d\x3a\xdf\xff\xff\xdd\x12\x00\x00\x00\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00\x00\x2a\x00\x00\x00\x00\x00\x00\x00";
+
+    let (tx, rx) = mpsc::channel();
+    let options = DecodeOptions::default()
+        .structural_only(true)
+        .on_warning(move |warning| tx.send(warning).expect("failed to send warning"));
+    let mut decoded = Vec::new();
+    Decoder::new_with_options(corrupt_data.as_slice(), options)
+        .decode(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(decoded.as_slice(), b"hello\n");
+    assert!(rx.try_iter().next().is_none());
+
+    // The sizes are still verified.
+    let mut corrupt_data = corrupt_data.to_vec();
+    corrupt_data[22 + 4] ^= 0x01;
+    let options = DecodeOptions::default().structural_only(true);
+    let result =
+        Decoder::new_with_options(corrupt_data.as_slice(), options).decode(&mut Vec::new());
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 0,
+            offset: 22,
+            error: Box::new(LzipError::InvalidDataSize),
+        }
+    );
+}
+
+#[test]
+fn member_error_policy() {
+    let mut corrupt_data: Vec<u8> = Vec::new();
+    Encoder::new(b"first".as_slice())
+        .encode(&mut corrupt_data)
+        .expect("failed to encode");
+    let first_len = corrupt_data.len();
+    Encoder::new(b"second".as_slice())
+        .encode(&mut corrupt_data)
+        .expect("failed to encode");
+    corrupt_data[first_len - 20] ^= 0xFF; // corrupt the first member's CRC
+
+    let decode = |policy| {
+        let options = DecodeOptions::default()
+            .lzma_flags(CONCATENATED)
+            .on_member_error(policy);
+        let mut decoder = Decoder::new_with_options(corrupt_data.as_slice(), options);
+        let mut decoded = Vec::new();
+        decoder.decode(&mut decoded).map(|stats| (decoded, stats))
+    };
+
+    let result = decode(MemberErrorPolicy::Abort);
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 0,
+            offset: first_len as u64 - 20,
+            error: Box::new(LzipError::InvalidCrc),
+        }
+    );
+
+    let (decoded, stats) = decode(MemberErrorPolicy::Skip).expect("failed to decode");
+    assert_eq!(decoded.as_slice(), b"second");
+    assert_eq!(stats.members, 2);
+    assert_eq!(stats.failed_members, [0]);
+
+    let (decoded, stats) = decode(MemberErrorPolicy::Emit).expect("failed to decode");
+    assert_eq!(decoded.as_slice(), b"firstsecond");
+    assert_eq!(stats.failed_members, [0]);
+}
+
+#[test]
+fn ratio_exceeded() {
+    // Incompressible data to get past the ratio threshold, followed by highly compressible data.
+    let mut state = 0x2545_f491_u32;
+    let mut input: Vec<u8> = (0..MIN_RATIO_INPUT * 2)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    input.resize(input.len() + (8 << 20), 0);
+
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new_with_level(input.as_slice(), CompressionLevel::Fastest);
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let options = DecodeOptions::default().max_ratio(10.0);
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::RatioExceeded);
+
+    let options = DecodeOptions::default().max_ratio(100.0);
+    let mut decoded = Vec::new();
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, input);
+}
+
+#[test]
+fn ratio_below_threshold() {
+    // Small inputs are never rejected, however well they compress.
+    let input = vec![0; 1 << 20];
+
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let options = DecodeOptions::default().max_ratio(1.0);
+    let mut decoded = Vec::new();
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, input);
+}
+
+#[test]
+fn error_source() {
+    let truncated = b"LZIP\x01";
+
+    let mut decoder = Decoder::new(truncated.as_slice());
+    let err = decoder.decode(&mut Vec::new()).unwrap_err();
+
+    let source = err.source().expect("missing error source");
+    let io_err = source
+        .downcast_ref::<io::Error>()
+        .expect("source is not an io::Error");
+    assert_eq!(io_err.kind(), io::ErrorKind::UnexpectedEof);
+    assert_eq!(
+        err,
+        LzipError::from(io::Error::from(io::ErrorKind::UnexpectedEof))
+    );
+
+    let err = LzipError::from(stream::Error::Data);
+    let source = err.source().expect("missing error source");
+    assert_eq!(
+        source.downcast_ref::<stream::Error>(),
+        Some(&stream::Error::Data)
+    );
+
+    assert!(LzipError::InvalidCrc.source().is_none());
+}
+
+#[test]
+fn trailer_format_version0() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    // A version 0 member has the same header apart from the version, and no member size.
+    let mut legacy = encoded.clone();
+    legacy[4] = 0;
+    legacy.truncate(legacy.len() - 8);
+
+    let options = DecodeOptions::default().trailer_format(TrailerFormat::Version0);
+    let mut decoded = Vec::new();
+    let mut decoder = Decoder::new_with_options(legacy.as_slice(), options.clone());
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, input);
+
+    let mut decoder = Decoder::new(legacy.as_slice());
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::UnsupportedVersion);
+
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::UnsupportedVersion);
+}
+
+#[test]
+fn warning_on_ignored_crc() {
+    let corrupt_data = b"LZIP\x01\x0c\x00\x34\x19\x49\xee\x8d\xdd\x3d\x3a\xdf\xff\xff\xdd\x12\x00\x00\x00\x00\x00\x00\x06\x00\x00\x00\x00\x00\x00\x00\x2a\x00\x00\x00\x00\x00\x00\x00";
+
+    let (tx, rx) = mpsc::channel();
+    let options = DecodeOptions::default()
+        .lzma_flags(IGNORE_CHECK)
+        .on_warning(move |warning| tx.send(warning).expect("failed to send"));
+    let mut decoder = Decoder::new_with_options(corrupt_data.as_slice(), options);
+    decoder.decode(&mut Vec::new()).expect("failed to decode");
+    drop(decoder);
+
+    let warnings: Vec<DecodeWarning> = rx.iter().collect();
+    assert_eq!(
+        warnings,
+        [DecodeWarning::CrcMismatch {
+            member: 0,
+            expected: 0,
+            got: crc32fast::hash(b"hello\n"),
+        }]
+    );
+}
+
+#[test]
+fn corrupt_offset() {
+    let input: Vec<u8> = (0..64 * 1024u32).map(|i| (i * 7 % 251) as u8).collect();
+    let mut corrupt_data: Vec<u8> = Vec::new();
+    for _ in 0..2 {
+        Encoder::new(input.as_slice())
+            .encode(&mut corrupt_data)
+            .expect("failed to encode");
+    }
+    let member_len = corrupt_data.len() / 2;
+
+    // Corrupt a byte in the middle of the second member's compressed data.
+    let corrupt_at = member_len + member_len / 2;
+    corrupt_data[corrupt_at] ^= 0x55;
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(corrupt_data.as_slice(), options);
+    let result = decoder.decode(&mut Vec::new());
+
+    let Err(LzipError::Corrupt { member, offset, .. }) = result else {
+        panic!("expected a corrupt member, got {:?}", result);
+    };
+    assert_eq!(member, 1);
+    assert!(offset >= corrupt_at as u64);
+    assert!(offset <= (2 * member_len - 20) as u64);
+}
+
+#[test]
+fn decode_best_effort() {
+    let input: Vec<u8> = (0..256 * 1024u32)
+        .map(|i| (i.wrapping_mul(i) % 251) as u8)
+        .collect();
+    let mut corrupt_data = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+    corrupt_data.truncate(corrupt_data.len() / 2);
+
+    let mut decoder = Decoder::new(corrupt_data.as_slice());
+    assert!(decoder.decode(&mut Vec::new()).is_err());
+
+    let (tx, rx) = mpsc::channel();
+    let options = DecodeOptions::default()
+        .on_warning(move |warning| tx.send(warning).expect("failed to send"));
+    let mut decoder = Decoder::new_with_options(corrupt_data.as_slice(), options);
+    let mut recovered: Vec<u8> = Vec::new();
+    let stats = decoder
+        .decode_best_effort(&mut recovered)
+        .expect("failed to recover");
+    drop(decoder);
+
+    let failure = stats.failure.expect("expected a failure");
+    assert_eq!(failure.member, 0);
+    assert_eq!(failure.offset, corrupt_data.len() as u64);
+    assert_eq!(failure.recovered_size, recovered.len() as u64);
+    assert!(!recovered.is_empty());
+    assert!(recovered.len() < input.len());
+    assert_eq!(recovered, input[..recovered.len()]);
+
+    let warnings: Vec<DecodeWarning> = rx.iter().collect();
+    assert!(matches!(
+        warnings.as_slice(),
+        [DecodeWarning::DecodeFailed { offset, .. }] if *offset == failure.offset
+    ));
+
+    // Intact data is decoded as usual.
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+    let mut decoded: Vec<u8> = Vec::new();
+    let stats = Decoder::new(encoded.as_slice())
+        .decode_best_effort(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(stats.failure, None);
+    assert_eq!(decoded, input);
+
+    // A damaged header is reported against its own member.
+    let members: [&[u8]; 2] = [b"the quick brown fox ", b"jumps over the lazy dog"];
+    let mut encoded: Vec<u8> = Vec::new();
+    for member in members {
+        Encoder::new(member)
+            .encode(&mut encoded)
+            .expect("failed to encode");
+    }
+    let second = split_members(&encoded).expect("failed to split")[0].len();
+    encoded[second + 4] = 0x07;
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut recovered: Vec<u8> = Vec::new();
+    let stats = Decoder::new_with_options(encoded.as_slice(), options)
+        .decode_best_effort(&mut recovered)
+        .expect("failed to recover");
+    let failure = stats.failure.expect("expected a failure");
+    assert_eq!(failure.member, 1);
+    assert_eq!(failure.recovered_size, members[0].len() as u64);
+    assert_eq!(recovered, members[0]);
+
+    // Failures outside the data are returned rather than recovered from.
+    let cancelled = Arc::new(AtomicBool::new(true));
+    let options = DecodeOptions::default().cancel_token(cancelled);
+    let result = Decoder::new_with_options(corrupt_data.as_slice(), options)
+        .decode_best_effort(&mut Vec::new());
+    assert_eq!(result, Err(LzipError::Cancelled));
+
+    let result =
+        Decoder::new(b"not lzip data at all".as_slice()).decode_best_effort(&mut Vec::new());
+    assert_eq!(result, Err(LzipError::InvalidMagic));
+}
+
+#[test]
+fn verify_against_manifest() {
+    let members = [
+        b"the quick brown fox ".as_slice(),
+        b"jumps over the lazy dog",
+    ];
+    let mut data: Vec<u8> = Vec::new();
+    for member in members {
+        Encoder::new(member)
+            .encode(&mut data)
+            .expect("failed to encode");
+    }
+    let manifest: Vec<MemberInfo> = members
+        .iter()
+        .map(|member| MemberInfo {
+            crc32: crc32fast::hash(member),
+            uncompressed_size: member.len() as u64,
+        })
+        .collect();
+
+    let verify = |manifest: &[MemberInfo]| {
+        let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+        Decoder::new_with_options(data.as_slice(), options).verify_against(manifest)
+    };
+
+    assert_eq!(verify(&manifest), Ok(()));
+
+    let mut wrong_crc = manifest.clone();
+    wrong_crc[1].crc32 ^= 1;
+    assert_eq!(
+        verify(&wrong_crc),
+        Err(LzipError::MemberError(1, Box::new(LzipError::InvalidCrc)))
+    );
+
+    let mut wrong_size = manifest.clone();
+    wrong_size[0].uncompressed_size += 1;
+    assert_eq!(
+        verify(&wrong_size),
+        Err(LzipError::MemberError(
+            0,
+            Box::new(LzipError::InvalidDataSize)
+        ))
+    );
+
+    assert_eq!(
+        verify(&manifest[..1]),
+        Err(LzipError::MemberError(
+            1,
+            Box::new(LzipError::InvalidDataSize)
+        ))
+    );
+
+    let mut missing = manifest.clone();
+    missing.push(manifest[0]);
+    assert_eq!(
+        verify(&missing),
+        Err(LzipError::MemberError(
+            2,
+            Box::new(LzipError::UnexpectedEndOfStream)
+        ))
+    );
 }
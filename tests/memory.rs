@@ -0,0 +1,174 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Memory bounds of decoding and verifying, measured with a counting global allocator.
+//!
+//! This binary holds a single test run by default so no other test allocates concurrently, and
+//! an ignored one for a larger input, to be run on its own with `--ignored`. Only allocations
+//! made from Rust are counted; liblzma allocates its dictionary through the C allocator.
+
+use lzipper::{CompressionLevel, Decoder, Encoder, EncoderOptions, LzipError};
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io::{self, Cursor, Read},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A global allocator tracking the current and peak number of allocated bytes.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_constant_memory() {
+    check_streaming(100 * 1024 * 1024); // 100 MiB of a repeating pattern
+
+    // Verifying members in parallel streams them to the threads instead of reading them
+    // whole. Random data keeps the 2 MiB members about as large compressed.
+    let reader = RandomReader {
+        state: 0x2545_f491,
+        remaining: 8 * 1024 * 1024,
+    };
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Fastest)
+        .block_size(2 * 1024 * 1024);
+    let mut encoded: Vec<u8> = Vec::new();
+    Encoder::new_with_options(reader, options)
+        .encode(&mut encoded)
+        .expect("failed to encode");
+    assert!(encoded.len() > 8 * 1024 * 1024);
+
+    for (threads, limit) in [(1, 1024 * 1024), (4, 2 * 1024 * 1024)] {
+        let baseline = ALLOCATED.load(Ordering::Relaxed);
+        PEAK.store(baseline, Ordering::Relaxed);
+
+        let mut decoder = Decoder::new(Cursor::new(encoded.as_slice()));
+        decoder.verify_parallel(threads).expect("failed to verify");
+        drop(decoder);
+
+        let peak = PEAK.load(Ordering::Relaxed) - baseline;
+        assert!(
+            peak < limit,
+            "peak allocation of {peak} bytes with {threads} threads"
+        );
+    }
+}
+
+#[test]
+#[ignore = "slow; run alone with --ignored"]
+fn test_constant_memory_large() {
+    check_streaming(500 * 1024 * 1024); // 500 MiB of a repeating pattern
+}
+
+/// Checks that testing and decoding `size` bytes of a single member to a sink allocate less
+/// than 1 MiB, and that the CRC32 is checked on the way.
+fn check_streaming(size: u64) {
+    let reader = PatternReader {
+        pattern: b"the quick brown fox jumps over the lazy dog\n",
+        pos: 0,
+        remaining: size,
+    };
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new_with_level(reader, CompressionLevel::Fastest);
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let mut decoder = Decoder::new(encoded.as_slice());
+    let stats = decoder.test().expect("failed to test");
+    assert_eq!(stats.members, 1);
+    drop(decoder);
+
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+    assert!(peak < 1024 * 1024, "peak allocation of {peak} bytes");
+
+    // Decoding to a sink verifies the CRC32 the same way, without holding on to the output.
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let mut decoder = Decoder::new(encoded.as_slice());
+    let stats = decoder.decode(&mut io::sink()).expect("failed to decode");
+    assert_eq!(stats.members, 1);
+    assert!(stats.failed_members.is_empty());
+    drop(decoder);
+
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+    assert!(peak < 1024 * 1024, "peak allocation of {peak} bytes");
+
+    // The CRC32 is really checked: a corrupted trailer fails the decode.
+    let crc_offset = encoded.len() - 20;
+    encoded[crc_offset] ^= 0xFF;
+    let mut decoder = Decoder::new(encoded.as_slice());
+    assert!(matches!(
+        decoder.decode(&mut io::sink()),
+        Err(LzipError::Corrupt { .. })
+    ));
+    drop(decoder);
+}
+
+/// A reader yielding `remaining` pseudo-random bytes.
+struct RandomReader {
+    state: u32,
+    remaining: u64,
+}
+
+impl Read for RandomReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.remaining as usize);
+        for byte in &mut buf[..n] {
+            self.state ^= self.state << 13;
+            self.state ^= self.state >> 17;
+            self.state ^= self.state << 5;
+            *byte = self.state as u8;
+        }
+        self.remaining -= n as u64;
+
+        Ok(n)
+    }
+}
+
+/// A reader yielding `remaining` bytes of a repeating pattern.
+struct PatternReader {
+    pattern: &'static [u8],
+    pos: usize,
+    remaining: u64,
+}
+
+impl Read for PatternReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut n = 0;
+        while n < buf.len() && self.remaining > 0 {
+            let len = (self.pattern.len() - self.pos)
+                .min(buf.len() - n)
+                .min(self.remaining as usize);
+            buf[n..n + len].copy_from_slice(&self.pattern[self.pos..self.pos + len]);
+            self.pos = (self.pos + len) % self.pattern.len();
+            self.remaining -= len as u64;
+            n += len;
+        }
+
+        Ok(n)
+    }
+}
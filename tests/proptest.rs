@@ -0,0 +1,77 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::{CompressionLevel, Decoder, Encoder};
+
+use proptest::prelude::*;
+
+/// Generates `size` bytes from `seed`, repeating earlier output with the given probability so the
+/// data ranges from incompressible to highly compressible.
+fn generate(seed: u64, size: usize, repeat_percent: u64) -> Vec<u8> {
+    let mut state = seed | 1;
+    let mut next = move || {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+
+    let mut data = Vec::with_capacity(size);
+    while data.len() < size {
+        let r = next();
+        if !data.is_empty() && r % 100 < repeat_percent {
+            let start = (next() as usize) % data.len();
+            let len = ((next() as usize) % 256).min(size - data.len());
+            for i in 0..len {
+                data.push(data[start + i % (data.len() - start)]);
+            }
+        } else {
+            data.push(r as u8);
+        }
+    }
+
+    data
+}
+
+fn level() -> impl Strategy<Value = CompressionLevel> {
+    prop_oneof![
+        Just(CompressionLevel::Fastest),
+        Just(CompressionLevel::Fast),
+        Just(CompressionLevel::Default),
+        Just(CompressionLevel::Maximum),
+    ]
+}
+
+fn size() -> impl Strategy<Value = usize> {
+    prop_oneof![
+        0..64usize,
+        4090..4100usize,
+        0..256 * 1024usize,
+        0..3 * 1024 * 1024usize,
+    ]
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(24))]
+
+    #[test]
+    fn roundtrip(seed: u64, size in size(), repeat_percent in 0..100u64, level in level()) {
+        let input = generate(seed, size, repeat_percent);
+
+        let encoded = Encoder::new_with_level(input.as_slice(), level)
+            .finish_to_vec()
+            .expect("failed to encode");
+
+        let trailer = &encoded[encoded.len() - 20..];
+        prop_assert_eq!(&trailer[0..4], &crc32fast::hash(&input).to_le_bytes());
+        prop_assert_eq!(&trailer[4..12], &(input.len() as u64).to_le_bytes());
+        prop_assert_eq!(&trailer[12..20], &(encoded.len() as u64).to_le_bytes());
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut decoder = Decoder::new(encoded.as_slice());
+        decoder.decode(&mut decoded).expect("failed to decode");
+
+        prop_assert_eq!(input, decoded);
+    }
+}
@@ -1,12 +1,22 @@
 // SPDX-License-Identifier: GPL-2.0-or-later
 // SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
 
-use lzipper::{CompressionLevel, Decoder, Encoder};
+use lzipper::decoder::CONCATENATED;
+use lzipper::{
+    BufferPool, CompressionLevel, Compressor, DecodeOptions, Decoder, DecoderState, Encoder,
+    EncoderOptions, KnownLength, LzipError, MaybeDecoder, OutputSink, Trailer, compress_in_place,
+    lzma1,
+};
 
 use std::{
+    cell::RefCell,
+    collections::VecDeque,
     fs::File,
-    io::{Cursor, Read, Write},
-    time::{SystemTime, UNIX_EPOCH},
+    io::{self, BufRead, BufReader, Cursor, Read, Write},
+    rc::Rc,
+    sync::{Arc, mpsc},
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
 #[test]
@@ -104,3 +114,1671 @@ fn roundtrip_max_compression_level() {
 
     assert_eq!(input, decoded.as_slice());
 }
+
+#[test]
+fn roundtrip_in_place() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+    let data = input.clone();
+
+    // Shrinking may move the result, so the allocation being reused isn't observable here.
+    let encoded = compress_in_place(data, CompressionLevel::Default).expect("failed to encode");
+
+    // The result is exactly one member, without the input's capacity.
+    let trailer = Trailer::from_bytes(encoded.last_chunk().unwrap());
+    assert_eq!(encoded.len() as u64, trailer.member_size);
+    assert!(encoded.capacity() < input.len());
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded);
+}
+
+#[test]
+fn roundtrip_after_header_validation() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    let dict_size = decoder
+        .validate_header_only()
+        .expect("failed to validate header");
+    assert_eq!(dict_size, 1 << 23);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded.as_slice());
+}
+
+#[test]
+fn roundtrip_concatenated() {
+    let first = b"the quick brown fox ";
+    let second = b"jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    Encoder::new(first.as_slice())
+        .encode(&mut encoded)
+        .expect("failed to encode");
+    Encoder::new(second.as_slice())
+        .encode(&mut encoded)
+        .expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(&encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(first, decoded.as_slice());
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(&encoded), options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(
+        b"the quick brown fox jumps over the lazy dog",
+        decoded.as_slice()
+    );
+}
+
+#[test]
+fn roundtrip_mixed_dict_sizes() {
+    // A block repeated at a distance only the larger dictionary of the second member covers.
+    let mut state = 0x9e37_79b9_u32;
+    let block: Vec<u8> = (0..512 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let first = b"the quick brown fox ".repeat(1000);
+    let second = block.repeat(2);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    Encoder::new_with_level(first.as_slice(), CompressionLevel::Fastest)
+        .encode(&mut encoded)
+        .expect("failed to encode");
+    let first_len = encoded.len();
+    Encoder::new_with_level(second.as_slice(), CompressionLevel::Maximum)
+        .encode(&mut encoded)
+        .expect("failed to encode");
+    assert_ne!(encoded[5], encoded[first_len + 5]);
+    assert!(encoded.len() - first_len < second.len() * 3 / 4);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(&encoded), options);
+    let stats = decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!([first, second].concat(), decoded);
+    assert_eq!(stats.members, 2);
+    assert_eq!(stats.inferred_level, None);
+}
+
+#[test]
+fn roundtrip_min_dict_size() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let options = EncoderOptions::default().dict_size(1 << 12); // 4 KiB
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+    encoder.encode(&mut encoded).expect("failed to encode");
+    assert_eq!(encoded[5], 0x0C);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded.as_slice());
+}
+
+#[test]
+fn roundtrip_max_dict_size() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Fastest)
+        .dict_size(1 << 29); // 512 MiB
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+    encoder.encode(&mut encoded).expect("failed to encode");
+    assert_eq!(encoded[5], 0x1D);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded.as_slice());
+}
+
+#[test]
+fn roundtrip_lines() {
+    let input = b"the quick brown fox\njumps over\nthe lazy dog\n";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let decoder = Decoder::new(Cursor::new(encoded));
+    let lines: Vec<String> = decoder
+        .lines()
+        .collect::<Result<_, _>>()
+        .expect("failed to decode");
+
+    assert_eq!(lines, ["the quick brown fox", "jumps over", "the lazy dog"]);
+}
+
+#[test]
+fn roundtrip_read() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.read_to_end(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded);
+}
+
+#[test]
+fn roundtrip_channel() {
+    let (tx, rx) = mpsc::channel();
+
+    let producer = thread::spawn(move || {
+        for i in 0..100u8 {
+            tx.send(vec![i; 1000]).expect("failed to send");
+        }
+    });
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::from_receiver(rx, EncoderOptions::default());
+    encoder.encode(&mut encoded).expect("failed to encode");
+    producer.join().expect("producer panicked");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    let expected: Vec<u8> = (0..100u8).flat_map(|i| vec![i; 1000]).collect();
+    assert_eq!(expected, decoded);
+}
+
+#[test]
+fn roundtrip_result_iter() {
+    let input = b"the quick brown fox jumps over the lazy dog".repeat(100);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let chunks: Vec<io::Result<Vec<u8>>> = encoded.chunks(7).map(|c| Ok(c.to_vec())).collect();
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::from_result_iter(chunks.into_iter());
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(input, decoded);
+
+    let mut chunks: Vec<io::Result<Vec<u8>>> = encoded.chunks(7).map(|c| Ok(c.to_vec())).collect();
+    chunks[3] = Err(io::Error::new(io::ErrorKind::TimedOut, "page 3 failed"));
+    let mut decoder = Decoder::from_result_iter(chunks.into_iter());
+    let err = decoder.decode(&mut Vec::new()).unwrap_err();
+    match err {
+        LzipError::IoError(err) => {
+            assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+            assert_eq!(err.to_string(), "page 3 failed");
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+}
+
+#[test]
+fn roundtrip_compressed_bytes_consumed() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(1000);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    for half in input.chunks(input.len() / 2) {
+        let mut encoder = Encoder::new(half);
+        encoder.encode(&mut encoded).expect("failed to encode");
+    }
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(&encoded), options);
+    assert_eq!(decoder.compressed_bytes_consumed(), 0);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut buf = [0; 256];
+    let mut consumed = 0;
+    loop {
+        let read = decoder.read(&mut buf).expect("failed to read");
+        assert!(decoder.compressed_bytes_consumed() >= consumed);
+        consumed = decoder.compressed_bytes_consumed();
+        if read == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&buf[..read]);
+    }
+
+    assert_eq!(input, decoded);
+    assert_eq!(consumed, encoded.len() as u64);
+}
+
+#[test]
+fn roundtrip_map() {
+    let input = b"a banana and a papaya".repeat(100);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder
+        .decode_map(&mut decoded, |chunk| {
+            chunk
+                .iter()
+                .map(|&b| if b == b'a' { b'b' } else { b })
+                .collect()
+        })
+        .expect("failed to decode");
+
+    let expected: Vec<u8> = input
+        .iter()
+        .map(|&b| if b == b'a' { b'b' } else { b })
+        .collect();
+    assert_eq!(expected, decoded);
+}
+
+#[test]
+fn roundtrip_tee() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut first: Vec<u8> = Vec::new();
+    let mut second: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder
+        .decode_tee(&mut [&mut first, &mut second])
+        .expect("failed to decode");
+
+    assert_eq!(input, first.as_slice());
+    assert_eq!(input, second.as_slice());
+}
+
+#[test]
+fn roundtrip_empty_members() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    for member in [b"".as_slice(), input.as_slice(), b"".as_slice()] {
+        let mut encoder = Encoder::new(member);
+        encoder.encode(&mut encoded).expect("failed to encode");
+    }
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(encoded), options);
+    let stats = decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded.as_slice());
+    assert_eq!(stats.members, 3);
+}
+
+#[test]
+fn decode_empty_file() {
+    // The exact output of `lzip` for an empty input.
+    let encoded = b"LZIP\x01\x0c\x00\x83\xff\xfb\xff\xff\xc0\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x24\x00\x00\x00\x00\x00\x00\x00";
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(encoded.as_slice());
+    let stats = decoder.decode(&mut decoded).expect("failed to decode");
+    assert!(decoded.is_empty());
+    assert_eq!(stats.members, 1);
+
+    let trailer = Trailer::from_bytes(encoded[encoded.len() - Trailer::SIZE..].try_into().unwrap());
+    assert_eq!(trailer.crc32, 0);
+    assert_eq!(trailer.crc32, crc32fast::hash(&decoded));
+}
+
+#[test]
+fn roundtrip_lzma1() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+    let options = EncoderOptions::default().level(CompressionLevel::Fast);
+
+    let encoded = lzma1::encode(&input, &options).expect("failed to encode");
+    assert_ne!(&encoded[..4], b"LZIP");
+
+    let decoded = lzma1::decode(&encoded, 1 << 22).expect("failed to decode");
+    assert_eq!(input, decoded);
+}
+
+#[test]
+fn roundtrip_large_final_flush() {
+    // The compressed data is consumed long before the decompressed data is flushed.
+    let input = vec![0; 16 * 1024 * 1024]; // 16 MiB of zeros
+    let options = EncoderOptions::default().level(CompressionLevel::Fastest);
+
+    let encoded = lzma1::encode(&input, &options).expect("failed to encode");
+    let decoded = lzma1::decode(&encoded, 1 << 18).expect("failed to decode");
+    assert_eq!(input, decoded);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    let mut buf = [0; 512];
+    loop {
+        let read = decoder.read(&mut buf).expect("failed to read");
+        if read == 0 {
+            break;
+        }
+        decoded.extend_from_slice(&buf[..read]);
+    }
+    assert_eq!(input, decoded);
+}
+
+#[test]
+fn roundtrip_headerless() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let options = EncoderOptions::default().dict_size(1 << 16);
+
+    let mut payload: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+    let (crc32, size) = encoder
+        .encode_headerless(&mut payload)
+        .expect("failed to encode");
+    assert_ne!(&payload[..4], b"LZIP");
+    assert_eq!(crc32, crc32fast::hash(input));
+    assert_eq!(size, input.len() as u64);
+    assert!(encoder.self_check());
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new_headerless(payload.as_slice(), 1 << 16, crc32, size)
+        .expect("failed to create decoder");
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(input, decoded.as_slice());
+
+    let mut decoder = Decoder::new_headerless(payload.as_slice(), 1 << 16, !crc32, size)
+        .expect("failed to create decoder");
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 0,
+            offset: payload.len() as u64,
+            error: Box::new(LzipError::InvalidCrc),
+        }
+    );
+}
+
+#[test]
+fn roundtrip_assume_raw() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let options = EncoderOptions::default().dict_size(1 << 16);
+    let encoded = lzma1::encode(input, &options).expect("failed to encode");
+
+    let mut decoder = Decoder::new(encoded.as_slice());
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::InvalidMagic);
+
+    let options = DecodeOptions::default().assume_raw(Some(1 << 16));
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options.clone());
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(input, decoded.as_slice());
+
+    // Input with the magic is still decoded as lzip.
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(input, decoded.as_slice());
+}
+
+#[test]
+fn roundtrip_small_buffer() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let mut encoded: Vec<u8> = Vec::new();
+    for _ in 0..2 {
+        let mut encoder = Encoder::new(input.as_slice());
+        encoder.encode(&mut encoded).expect("failed to encode");
+    }
+
+    // The header and trailer are larger than the reader's buffer.
+    let reader = BufReader::with_capacity(4, Cursor::new(encoded));
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(reader, options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!([input.as_slice(), input.as_slice()].concat(), decoded);
+}
+
+#[test]
+fn roundtrip_parallel() {
+    let input: Vec<u8> = (0..1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Fastest)
+        .block_size(100 * 1024)
+        .threads(4);
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(encoded), options);
+    let stats = decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded);
+    assert_eq!(stats.members, 11);
+}
+
+#[test]
+fn roundtrip_finish_to_vec() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded.as_slice());
+}
+
+#[test]
+fn roundtrip_encode_append() {
+    let inputs: [&[u8]; 3] = [b"the quick brown fox ", b"", b"jumps over the lazy dog"];
+
+    let mut appended = b"prefix".to_vec();
+    let mut encoded: Vec<u8> = b"prefix".to_vec();
+    for input in inputs {
+        Encoder::new(input)
+            .encode_append(&mut appended)
+            .expect("failed to encode");
+        Encoder::new(input)
+            .encode(&mut encoded)
+            .expect("failed to encode");
+    }
+    assert_eq!(appended, encoded);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(&appended[6..], options);
+    let stats = decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(inputs.concat(), decoded);
+    assert_eq!(stats.members, 3);
+}
+
+#[test]
+fn roundtrip_buffer_pool() {
+    let pool = BufferPool::new(4096);
+    let encoder_options = EncoderOptions::default()
+        .block_size(16)
+        .buffer_pool(pool.clone());
+    let decode_options = DecodeOptions::default()
+        .lzma_flags(CONCATENATED)
+        .buffer_pool(pool.clone());
+
+    let mut message = b"the quick brown fox\njumps over the lazy dog\n".to_vec();
+    for _ in 0..10 {
+        let mut encoded: Vec<u8> = Vec::new();
+        let mut encoder = Encoder::new_with_options(message.as_slice(), encoder_options.clone());
+        encoder.encode(&mut encoded).expect("failed to encode");
+
+        let decoder = Decoder::new_with_options(Cursor::new(encoded), decode_options.clone());
+        let lines: Vec<String> = decoder
+            .lines()
+            .collect::<Result<_, _>>()
+            .expect("failed to read lines");
+        assert_eq!(lines, ["the quick brown fox", "jumps over the lazy dog"]);
+
+        message = format!("{}\n{}\n", lines[0], lines[1]).into_bytes();
+    }
+
+    // The buffers of one iteration are reused by the next instead of accumulating.
+    assert!((1..=4).contains(&pool.idle()));
+}
+
+#[test]
+fn roundtrip_reserve() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(10_000);
+
+    let encoded = Encoder::new_sized(input.as_slice(), EncoderOptions::default())
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert!(encoded.capacity() >= input.len() / 2);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded);
+}
+
+#[test]
+fn roundtrip_compressor() {
+    let messages: Vec<Vec<u8>> = (0..100)
+        .map(|i| format!("message number {i}").into_bytes())
+        .collect();
+
+    let mut compressor = Compressor::new(EncoderOptions::default().dict_size(1 << 16))
+        .expect("failed to create compressor");
+    let mut out: Vec<u8> = Vec::new();
+    for message in &messages {
+        out.clear();
+        compressor
+            .compress_one(message, &mut out)
+            .expect("failed to compress");
+
+        let encoded = Encoder::new_with_options(
+            message.as_slice(),
+            EncoderOptions::default().dict_size(1 << 16),
+        )
+        .finish_to_vec()
+        .expect("failed to encode");
+        assert_eq!(out, encoded);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut decoder = Decoder::new(out.as_slice());
+        decoder.decode(&mut decoded).expect("failed to decode");
+        assert_eq!(message, &decoded);
+    }
+}
+
+#[test]
+fn roundtrip_callback() {
+    let input: Vec<u8> = (0..4 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new_with_level(input.as_slice(), CompressionLevel::Fastest);
+    encoder.encode(&mut encoded).expect("failed to encode");
+
+    let mut buf = [0u8; 256];
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder
+        .decode_with_callback(&mut buf, |chunk| {
+            assert!(chunk.len() == 256 || decoded.len() + chunk.len() == input.len());
+            decoded.extend_from_slice(chunk);
+            Ok(())
+        })
+        .expect("failed to decode");
+
+    assert_eq!(input, decoded);
+
+    // An empty buffer is rejected rather than looping without progress.
+    let result = Decoder::new(Cursor::new(Vec::new())).decode_with_callback(&mut [], |_| Ok(()));
+    assert!(
+        matches!(result, Err(LzipError::IoError(err)) if err.kind() == io::ErrorKind::InvalidInput)
+    );
+}
+
+#[test]
+fn roundtrip_callback_window_overlap() {
+    let input: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let mut buf = [0u8; 1000];
+    let mut windows: Vec<Vec<u8>> = Vec::new();
+    let options = DecodeOptions::default().window_overlap(64);
+    Decoder::new_with_options(encoded.as_slice(), options)
+        .decode_with_callback(&mut buf, |window| {
+            windows.push(window.to_vec());
+            Ok(())
+        })
+        .expect("failed to decode");
+
+    for pair in windows.windows(2) {
+        assert_eq!(pair[0].len(), 1000);
+        assert_eq!(pair[1][..64], pair[0][1000 - 64..]);
+    }
+
+    // Dropping the repeated bytes restores the data.
+    let mut decoded = windows[0].clone();
+    for window in &windows[1..] {
+        decoded.extend_from_slice(&window[64..]);
+    }
+    assert_eq!(decoded, input);
+    // A buffer no larger than the overlap can't make progress.
+    let options = DecodeOptions::default().window_overlap(64);
+    let result = Decoder::new_with_options(encoded.as_slice(), options)
+        .decode_with_callback(&mut [0u8; 64], |_| Ok(()));
+    assert!(
+        matches!(result, Err(LzipError::IoError(err)) if err.kind() == io::ErrorKind::InvalidInput)
+    );
+}
+
+#[test]
+fn roundtrip_auto_dict() {
+    let input = b"0123456789";
+
+    let options = EncoderOptions::default()
+        .auto_dict(true)
+        .input_size(input.len() as u64);
+    let encoded = Encoder::new_with_options(input.as_slice(), options)
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded[5], 0x0C); // 4 KiB
+
+    let options = EncoderOptions::default()
+        .auto_dict(true)
+        .input_size(100_000);
+    let encoded = Encoder::new_with_options(input.as_slice(), options)
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded[5], 0x71); // 104 KiB
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+
+    assert_eq!(input, decoded.as_slice());
+}
+
+#[test]
+fn roundtrip_probe_size() {
+    let small = b"the quick brown fox jumps over the lazy dog\n".repeat(10);
+    let large = b"the quick brown fox jumps over the lazy dog\n".repeat(10_000);
+
+    let encoded = Encoder::new(small.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded[5], 0x17); // 8 MiB
+
+    let options = EncoderOptions::default().probe_size(64 * 1024);
+    for (input, dict_size) in [(&small, 0x0C), (&large, 0x17)] {
+        let mut encoded: Vec<u8> = Vec::new();
+        Encoder::new_with_options(input.as_slice(), options.clone())
+            .encode(&mut encoded)
+            .expect("failed to encode");
+        assert_eq!(encoded[5], dict_size);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        Decoder::new(encoded.as_slice())
+            .decode(&mut decoded)
+            .expect("failed to decode");
+        assert_eq!(&decoded, input);
+    }
+}
+
+#[test]
+fn roundtrip_store_if_incompressible() {
+    let mut state = 0x2545_f491_u32;
+    let random: Vec<u8> = (0..64 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let text = b"the quick brown fox jumps over the lazy dog\n".repeat(1000);
+
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Maximum)
+        .store_if_incompressible(true);
+    for (input, level) in [
+        (random, CompressionLevel::Fastest),
+        (text, CompressionLevel::Maximum),
+    ] {
+        let mut encoded: Vec<u8> = Vec::new();
+        let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+        encoder.encode(&mut encoded).expect("failed to encode");
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut decoder = Decoder::new(Cursor::new(encoded));
+        let stats = decoder.decode(&mut decoded).expect("failed to decode");
+        assert_eq!(input, decoded);
+        assert_eq!(stats.inferred_level, Some(level));
+
+        let mut encoded: Vec<u8> = Vec::new();
+        let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+        encoder
+            .checkpoint(&mut encoded)
+            .expect("failed to checkpoint");
+        let stats = Decoder::new(encoded.as_slice())
+            .decode(&mut Vec::new())
+            .expect("failed to decode");
+        assert_eq!(stats.inferred_level, Some(level));
+    }
+}
+
+/// A reader yielding the data pushed to a shared queue so far, ending whenever it runs dry.
+struct QueueReader(Rc<RefCell<VecDeque<u8>>>);
+
+impl Read for QueueReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+#[test]
+fn roundtrip_store_if_incompressible_per_member() {
+    let mut state = 0x2545_f491_u32;
+    let random: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+    let text = b"the quick brown fox jumps over the lazy dog\n".repeat(5000);
+
+    // The fallback for incompressible input doesn't stick to the encoder.
+    let options = EncoderOptions::default()
+        .level(CompressionLevel::Maximum)
+        .store_if_incompressible(true);
+    let queue = Rc::new(RefCell::new(VecDeque::new()));
+    let mut encoder = Encoder::new_with_options(QueueReader(queue.clone()), options.clone());
+    for (input, level) in [
+        (&random, CompressionLevel::Fastest),
+        (&text, CompressionLevel::Maximum),
+    ] {
+        queue.borrow_mut().extend(input.iter());
+        let mut encoded: Vec<u8> = Vec::new();
+        encoder.encode(&mut encoded).expect("failed to encode");
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let stats = Decoder::new(encoded.as_slice())
+            .decode(&mut decoded)
+            .expect("failed to decode");
+        assert_eq!(&decoded, input);
+        assert_eq!(stats.inferred_level, Some(level));
+    }
+
+    // The sample doesn't depend on how the input splits up its reads.
+    let mut input = random.clone();
+    input.extend_from_slice(&text);
+    let expected = Encoder::new_with_options(input.as_slice(), options.clone())
+        .finish_to_vec()
+        .expect("failed to encode");
+    let reader = ChunkedReader {
+        data: &input,
+        calls: 0,
+    };
+    let encoded = Encoder::new_with_options(reader, options)
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded, expected);
+}
+
+#[test]
+fn compression_level_from_env() {
+    let name = "LZIPPER_TEST_LEVEL";
+    let cases = [
+        ("0", Some(CompressionLevel::Fastest)),
+        ("fast", Some(CompressionLevel::Fast)),
+        ("6", Some(CompressionLevel::Default)),
+        ("max", Some(CompressionLevel::Maximum)),
+        ("maximum", Some(CompressionLevel::Maximum)),
+        ("5", None),
+        ("Fast", None),
+        (" 9", None),
+        ("", None),
+    ];
+
+    for (value, level) in cases {
+        // SAFETY: no other test reads or writes this variable.
+        unsafe { std::env::set_var(name, value) };
+        assert_eq!(CompressionLevel::from_env(name), level, "value {value:?}");
+    }
+
+    // SAFETY: no other test reads or writes this variable.
+    unsafe { std::env::remove_var(name) };
+    assert_eq!(CompressionLevel::from_env(name), None);
+}
+
+#[test]
+fn error_on_expansion() {
+    let input = [0x8f, 0x1c, 0xe2, 0x47, 0x90, 0x3b, 0xd5, 0x6a, 0x01, 0xf8];
+    let options = EncoderOptions::default().error_on_expansion(true);
+
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+    let result = encoder.encode(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::Expanded);
+
+    let mut out = b"prefix".to_vec();
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+    let result = encoder.encode_append(&mut out);
+    assert_eq!(result.unwrap_err(), LzipError::Expanded);
+    assert_eq!(out, b"prefix");
+
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+    let result = encoder.checkpoint(&mut Vec::new());
+    assert_eq!(result.unwrap_err(), LzipError::Expanded);
+
+    let input = vec![0; 1024];
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+    encoder.encode(&mut Vec::new()).expect("failed to encode");
+}
+
+#[test]
+fn roundtrip_inferred_level() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+
+    for level in [CompressionLevel::Fastest, CompressionLevel::Maximum] {
+        let encoded = Encoder::new_with_level(input.as_slice(), level)
+            .finish_to_vec()
+            .expect("failed to encode");
+
+        let mut decoder = Decoder::new(Cursor::new(encoded));
+        let stats = decoder.decode(&mut Vec::new()).expect("failed to decode");
+        assert_eq!(stats.inferred_level, Some(level));
+    }
+
+    let options = EncoderOptions::default().dict_size(1 << 20);
+    let encoded = Encoder::new_with_options(input.as_slice(), options)
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    let stats = decoder.decode(&mut Vec::new()).expect("failed to decode");
+    assert_eq!(stats.inferred_level, None);
+}
+
+#[test]
+fn roundtrip_known_length() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let options = EncoderOptions::default().auto_dict(true);
+
+    assert_eq!(KnownLength::len(&input.as_slice()), Some(43));
+    let encoded = Encoder::new_sized(input.as_slice(), options.clone())
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded[5], 0x0C); // 4 KiB
+
+    let mut cursor = Cursor::new(input.to_vec());
+    cursor.set_position(3);
+    assert_eq!(KnownLength::len(&cursor), Some(40));
+    let encoded = Encoder::new_sized(cursor, options.clone())
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded[5], 0x0C); // 4 KiB
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let path = std::env::temp_dir().join(format!("test_known_length_{}.txt", timestamp));
+    std::fs::write(&path, vec![0; 100_000]).expect("failed to write input file");
+
+    let file = File::open(&path).expect("failed to open input file");
+    assert_eq!(KnownLength::len(&file), Some(100_000));
+    let encoded = Encoder::new_sized(file, options)
+        .finish_to_vec()
+        .expect("failed to encode");
+    assert_eq!(encoded[5], 0x71); // 104 KiB
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, vec![0; 100_000]);
+
+    std::fs::remove_file(&path).expect("failed to remove input file");
+}
+
+#[test]
+fn roundtrip_checkpoint() {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let log_path = std::env::temp_dir().join(format!("test_checkpoint_{}.log", timestamp));
+
+    let mut log = File::create(&log_path).expect("failed to create log file");
+    let reader = File::open(&log_path).expect("failed to open log file for reading");
+    let mut encoder = Encoder::new(reader);
+    let mut encoded: Vec<u8> = Vec::new();
+
+    let decode_all = |encoded: &[u8]| {
+        let mut decoded: Vec<u8> = Vec::new();
+        let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+        let mut decoder = Decoder::new_with_options(encoded, options);
+        decoder.decode(&mut decoded).expect("failed to decode");
+        decoded
+    };
+
+    log.write_all(b"the quick brown fox ")
+        .expect("failed to write log");
+    encoder
+        .checkpoint(&mut encoded)
+        .expect("failed to checkpoint");
+    assert_eq!(decode_all(&encoded), b"the quick brown fox ");
+
+    log.write_all(b"jumps over the lazy dog")
+        .expect("failed to write log");
+    encoder
+        .checkpoint(&mut encoded)
+        .expect("failed to checkpoint");
+    assert_eq!(
+        decode_all(&encoded),
+        b"the quick brown fox jumps over the lazy dog"
+    );
+
+    // Nothing was appended, so no empty member is written.
+    let len = encoded.len();
+    encoder
+        .checkpoint(&mut encoded)
+        .expect("failed to checkpoint");
+    assert_eq!(encoded.len(), len);
+
+    std::fs::remove_file(&log_path).expect("failed to remove log file");
+}
+
+#[test]
+fn roundtrip_repack() {
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut input = Vec::new();
+    for i in 0..100 {
+        let member = format!("member {} of the quick brown fox\n", i).repeat(800);
+        Encoder::new_with_level(member.as_bytes(), CompressionLevel::Fastest)
+            .encode(&mut encoded)
+            .expect("failed to encode");
+        input.extend_from_slice(member.as_bytes());
+    }
+    assert_eq!(
+        lzipper::split_members(&encoded)
+            .expect("failed to split")
+            .len(),
+        100
+    );
+
+    let mut repacked: Vec<u8> = Vec::new();
+    let options = EncoderOptions::default().level(CompressionLevel::Fastest);
+    lzipper::repack(encoded.as_slice(), &mut repacked, 1 << 20, options).expect("failed to repack");
+
+    let members = lzipper::split_members(&repacked).expect("failed to split");
+    assert_eq!(members.len(), input.len().div_ceil(1 << 20));
+    for member in &members[..members.len() - 1] {
+        assert_eq!(lzipper::uncompressed_size(member), Ok(1 << 20));
+    }
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(repacked.as_slice(), options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, input);
+}
+
+#[test]
+fn roundtrip_transcode() {
+    let words = [
+        "the", "quick", "brown", "fox", "jumps", "over", "lazy", "dog",
+    ];
+    let mut state: u32 = 1;
+    let mut input = Vec::new();
+    while input.len() < 256 * 1024 {
+        state = state.wrapping_mul(1103515245).wrapping_add(12345);
+        input.extend_from_slice(words[(state >> 16) as usize % words.len()].as_bytes());
+        input.push(b' ');
+    }
+    let (first, second) = input.split_at(100 * 1024);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    for member in [first, second] {
+        Encoder::new_with_level(member, CompressionLevel::Fastest)
+            .encode(&mut encoded)
+            .expect("failed to encode");
+    }
+
+    let mut transcoded: Vec<u8> = Vec::new();
+    let options = EncoderOptions::default().level(CompressionLevel::Maximum);
+    lzipper::transcode(encoded.as_slice(), &mut transcoded, options).expect("failed to transcode");
+    assert!(transcoded.len() < encoded.len());
+
+    let members = lzipper::split_members(&transcoded).expect("failed to split");
+    assert_eq!(members.len(), 2);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(transcoded), options);
+    let stats = decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, input);
+    assert_eq!(stats.inferred_level, Some(CompressionLevel::Maximum));
+
+    // A corrupted input member fails with the decoder's error.
+    let last = encoded.len() - 20;
+    encoded[last] ^= 0xFF;
+    let result = lzipper::transcode(
+        encoded.as_slice(),
+        &mut Vec::new(),
+        EncoderOptions::default(),
+    );
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 1,
+            offset: last as u64,
+            error: Box::new(LzipError::InvalidCrc),
+        }
+    );
+}
+
+#[test]
+fn roundtrip_adaptive_buffer() {
+    let mut state: u32 = 1;
+    let incompressible: Vec<u8> = (0..1024 * 1024)
+        .map(|_| {
+            state = state.wrapping_mul(1103515245).wrapping_add(12345);
+            (state >> 16) as u8
+        })
+        .collect();
+    let compressible = vec![0; 1024 * 1024];
+
+    for input in [incompressible, compressible] {
+        let options = EncoderOptions::default()
+            .level(CompressionLevel::Fastest)
+            .adaptive_buffer(true)
+            .adaptive_buffer_cap(16 * 1024);
+        let mut encoded: Vec<u8> = Vec::new();
+        let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+        encoder.encode(&mut encoded).expect("failed to encode");
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut decoder = Decoder::new(Cursor::new(encoded));
+        decoder.decode(&mut decoded).expect("failed to decode");
+        assert_eq!(input, decoded);
+    }
+}
+
+#[test]
+fn roundtrip_all_byte_values() {
+    let ascending: Vec<u8> = (0..=255u8).cycle().take(256 * 1024).collect();
+    let descending: Vec<u8> = (0..=255u8).rev().cycle().take(256 * 1024).collect();
+    let runs: Vec<u8> = (0..=255u8)
+        .flat_map(|byte| std::iter::repeat_n(byte, byte as usize + 1))
+        .collect();
+    let nulls: Vec<u8> = (0..=255u8).flat_map(|byte| [0, byte, 0xFF]).collect();
+
+    let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+    let mut random = |size: usize| -> Vec<u8> {
+        (0..size)
+            .map(|_| {
+                // xorshift64
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 32) as u8
+            })
+            .collect()
+    };
+
+    let mut inputs = vec![ascending, descending, runs, nulls];
+    for size in [1, 255, 256, 4095, 4096, 65537, 1024 * 1024] {
+        inputs.push(random(size));
+    }
+
+    for input in inputs {
+        let mut encoded: Vec<u8> = Vec::new();
+        let mut encoder = Encoder::new(input.as_slice());
+        encoder.encode(&mut encoded).expect("failed to encode");
+
+        // The append path has to produce the exact same member.
+        let appended = Encoder::new(input.as_slice())
+            .finish_to_vec()
+            .expect("failed to encode");
+        assert_eq!(encoded, appended);
+
+        let mut decoded: Vec<u8> = Vec::new();
+        let mut decoder = Decoder::new(Cursor::new(&encoded));
+        decoder.decode(&mut decoded).expect("failed to decode");
+        assert_eq!(input, decoded);
+
+        let mut read: Vec<u8> = Vec::new();
+        Decoder::new(encoded.as_slice())
+            .read_to_end(&mut read)
+            .expect("failed to read");
+        assert_eq!(input, read);
+    }
+}
+
+/// A sink counting the decompressed bytes and chunks, failing once it has seen `limit` bytes.
+struct CountingSink {
+    bytes: u64,
+    chunks: u64,
+    limit: u64,
+}
+
+impl OutputSink for CountingSink {
+    fn write_chunk(&mut self, chunk: &[u8]) -> Result<(), LzipError> {
+        self.bytes += chunk.len() as u64;
+        self.chunks += 1;
+        if self.bytes >= self.limit {
+            return Err(LzipError::Cancelled);
+        }
+
+        Ok(())
+    }
+}
+
+#[test]
+fn roundtrip_output_sink() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let mut sink = CountingSink {
+        bytes: 0,
+        chunks: 0,
+        limit: u64::MAX,
+    };
+    let mut decoder = Decoder::new(encoded.as_slice());
+    decoder.decode(&mut sink).expect("failed to decode");
+    assert_eq!(sink.bytes, input.len() as u64);
+    assert!(sink.chunks > 1);
+
+    // An error from the sink aborts decoding.
+    let mut sink = CountingSink {
+        bytes: 0,
+        chunks: 0,
+        limit: 1,
+    };
+    let mut decoder = Decoder::new(encoded.as_slice());
+    let result = decoder.decode(&mut sink);
+    assert_eq!(result.unwrap_err(), LzipError::Cancelled);
+    assert_eq!(sink.chunks, 1);
+}
+
+/// A writer counting the flushes and the bytes written before the last one.
+struct FlushCountingWriter {
+    data: Vec<u8>,
+    flushes: u64,
+    flushed_len: usize,
+}
+
+impl Write for FlushCountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.data.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushes += 1;
+        self.flushed_len = self.data.len();
+        Ok(())
+    }
+}
+
+#[test]
+fn roundtrip_flushes_output() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(1000);
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let mut output = FlushCountingWriter {
+        data: Vec::new(),
+        flushes: 0,
+        flushed_len: 0,
+    };
+    Decoder::new(encoded.as_slice())
+        .decode(&mut output)
+        .expect("failed to decode");
+    assert_eq!(output.data, input);
+    assert_eq!(output.flushes, 1);
+    assert_eq!(output.flushed_len, input.len());
+
+    let mut output = FlushCountingWriter {
+        data: Vec::new(),
+        flushes: 0,
+        flushed_len: 0,
+    };
+    let options = DecodeOptions::default().flush_each_chunk(true);
+    Decoder::new_with_options(encoded.as_slice(), options)
+        .decode(&mut output)
+        .expect("failed to decode");
+    assert_eq!(output.data, input);
+    assert!(output.flushes > 2);
+    assert_eq!(output.flushed_len, input.len());
+}
+
+#[test]
+fn roundtrip_flush_interval() {
+    let mut state = 0x2545_f491_u32;
+    let input: Vec<u8> = (0..256 * 1024)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+
+    let mut output = FlushCountingWriter {
+        data: Vec::new(),
+        flushes: 0,
+        flushed_len: 0,
+    };
+    Encoder::new(input.as_slice())
+        .encode(&mut output)
+        .expect("failed to encode");
+    assert_eq!(output.flushes, 0);
+
+    let mut output = FlushCountingWriter {
+        data: Vec::new(),
+        flushes: 0,
+        flushed_len: 0,
+    };
+    let options = EncoderOptions::default().flush_interval(16 * 1024);
+    Encoder::new_with_options(input.as_slice(), options)
+        .encode(&mut output)
+        .expect("failed to encode");
+
+    // Each flush follows at least 16 KiB and less than 16 KiB plus one 4 KiB chunk.
+    let data_len = output.data.len() - 26;
+    assert!(output.flushes <= (data_len / (16 * 1024)) as u64);
+    assert!(output.flushes >= (data_len / (20 * 1024)) as u64);
+    assert!(output.flushes > 0);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    Decoder::new(output.data.as_slice())
+        .decode(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(decoded, input);
+}
+
+#[test]
+fn roundtrip_many_members() {
+    let member = b"the quick brown fox jumps over the lazy dog\n".repeat(10);
+    let options = EncoderOptions::default().dict_size(1 << 16);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut offsets = Vec::new();
+    for _ in 0..1000 {
+        Encoder::new_with_options(member.as_slice(), options.clone())
+            .encode(&mut encoded)
+            .expect("failed to encode");
+        offsets.push(encoded.len());
+    }
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let decode_options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let stats = Decoder::new_with_options(encoded.as_slice(), decode_options.clone())
+        .decode(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(stats.members, 1000);
+    assert_eq!(decoded, member.repeat(1000));
+
+    // Every member is still verified against its own trailer.
+    let crc_offset = offsets[500] - 20;
+    encoded[crc_offset] ^= 0xFF;
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), decode_options);
+    let result = decoder.decode(&mut Vec::new());
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::Corrupt {
+            member: 500,
+            offset: crc_offset as u64,
+            error: Box::new(LzipError::InvalidCrc),
+        }
+    );
+}
+
+/// A reader returning its data in chunks of varying size.
+struct ChunkedReader<'a> {
+    data: &'a [u8],
+    calls: usize,
+}
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.calls += 1;
+        let n = (self.calls * 7919 % 5000 + 1)
+            .min(buf.len())
+            .min(self.data.len());
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+
+        Ok(n)
+    }
+}
+
+#[test]
+fn deterministic_output() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(20_000);
+
+    for options in [
+        EncoderOptions::default(),
+        EncoderOptions::default().level(CompressionLevel::Fastest),
+        EncoderOptions::default()
+            .level(CompressionLevel::Maximum)
+            .block_size(256 * 1024),
+    ] {
+        let encode = |options: EncoderOptions| {
+            let mut encoded: Vec<u8> = Vec::new();
+            let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+            encoder.encode(&mut encoded).expect("failed to encode");
+            encoded
+        };
+
+        let first = encode(options.clone());
+        assert_eq!(first, encode(options.clone()));
+        assert_eq!(first, encode(options.clone().adaptive_buffer(true)));
+
+        // With more threads the input is split into members, at the same offsets every time.
+        let threaded = encode(options.clone().threads(2));
+        assert_eq!(threaded, encode(options.clone().threads(2)));
+
+        // How the input is split into reads doesn't matter either.
+        let reader = ChunkedReader {
+            data: &input,
+            calls: 0,
+        };
+        let mut chunked: Vec<u8> = Vec::new();
+        let mut encoder = Encoder::new_with_options(reader, options);
+        encoder.encode(&mut chunked).expect("failed to encode");
+        assert_eq!(first, chunked);
+    }
+}
+
+#[test]
+fn deterministic_threaded_output() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(20_000);
+    let options = EncoderOptions::default().block_size(256 * 1024);
+
+    let mut single: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+    encoder.encode(&mut single).expect("failed to encode");
+
+    let mut threaded: Vec<u8> = Vec::new();
+    let mut encoder = Encoder::new_with_options(input.as_slice(), options.threads(3));
+    encoder.encode(&mut threaded).expect("failed to encode");
+
+    assert_eq!(single, threaded);
+}
+
+#[test]
+fn roundtrip_member_size_invariant() {
+    let levels = [
+        CompressionLevel::Fastest,
+        CompressionLevel::Fast,
+        CompressionLevel::Default,
+        CompressionLevel::Maximum,
+    ];
+    let mut state = 0x2545_f491_u32;
+    let noise: Vec<u8> = (0..200_000)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as u8
+        })
+        .collect();
+
+    for level in levels {
+        for size in [0, 1, 20, 4095, 4096, 4097, 65_549, 200_000] {
+            // Half noise and half repetition, so both literals and matches are exercised.
+            let input: Vec<u8> = noise[..size / 2]
+                .iter()
+                .copied()
+                .chain((0..size - size / 2).map(|i| (i % 7) as u8))
+                .collect();
+
+            let options = EncoderOptions::default().level(level);
+            let mut encoded: Vec<u8> = Vec::new();
+            let mut encoder = Encoder::new_with_options(input.as_slice(), options.clone());
+            encoder.encode(&mut encoded).expect("failed to encode");
+            assert!(encoder.self_check(), "{level:?} {size}");
+
+            let mut appended: Vec<u8> = Vec::new();
+            let mut encoder = Encoder::new_with_options(input.as_slice(), options);
+            encoder
+                .encode_append(&mut appended)
+                .expect("failed to encode");
+            assert!(encoder.self_check(), "{level:?} {size}");
+            assert_eq!(appended, encoded);
+
+            let trailer = Trailer::from_bytes(encoded.last_chunk().expect("missing trailer"));
+            assert_eq!(trailer.member_size, encoded.len() as u64);
+
+            let mut decoded: Vec<u8> = Vec::new();
+            Decoder::new(encoded.as_slice())
+                .decode(&mut decoded)
+                .expect("failed to decode");
+            assert_eq!(decoded, input);
+        }
+    }
+}
+
+#[test]
+fn roundtrip_nonstandard_lzma_params() {
+    let input: Vec<u8> = (0..50_000u32)
+        .flat_map(|i| (i / 3 % 1000).to_le_bytes())
+        .collect();
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let options = EncoderOptions::default().lzma_params(3, 0, 0);
+    Encoder::new_with_options(input.as_slice(), options)
+        .encode(&mut encoded)
+        .expect("failed to encode");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_params(3, 0, 0);
+    Decoder::new_with_options(encoded.as_slice(), options)
+        .decode(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(decoded, input);
+
+    // The parameters aren't stored, so a standard decoder can't make sense of the data.
+    let result = Decoder::new(encoded.as_slice()).decode(&mut Vec::new());
+    assert!(result.is_err());
+
+    let options = EncoderOptions::default().lzma_params(3, 0, 5);
+    let result = Encoder::new_with_options(input.as_slice(), options).encode(&mut Vec::new());
+    assert!(result.is_err());
+}
+
+#[test]
+fn roundtrip_member_vecs() {
+    let members: [&[u8]; 3] = [b"the quick brown fox ", b"", b"jumps over the lazy dog"];
+    let mut encoded: Vec<u8> = Vec::new();
+    for member in members {
+        Encoder::new(member)
+            .encode(&mut encoded)
+            .expect("failed to encode");
+    }
+
+    let decoded: Vec<Vec<u8>> = Decoder::new(encoded.as_slice())
+        .into_member_vecs()
+        .collect::<Result<_, _>>()
+        .expect("failed to decode");
+    assert_eq!(decoded, members);
+
+    // The iteration ends at the first member failing verification.
+    let crc_offset = encoded.len() - 20;
+    encoded[crc_offset] ^= 0xFF;
+    let mut iter = Decoder::new(encoded.as_slice()).into_member_vecs();
+    assert_eq!(iter.next(), Some(Ok(members[0].to_vec())));
+    assert_eq!(iter.next(), Some(Ok(Vec::new())));
+    assert!(matches!(
+        iter.next(),
+        Some(Err(LzipError::Corrupt { member: 2, .. }))
+    ));
+    assert_eq!(iter.next(), None);
+    // An empty input has no member at all.
+    let mut iter = Decoder::new(&b""[..]).into_member_vecs();
+    assert_eq!(iter.next(), Some(Err(LzipError::UnexpectedEndOfStream)));
+    assert_eq!(iter.next(), None);
+}
+
+/// A reader failing with `kind` before every other read, `failures` times in a row.
+struct FlakyReader<'a> {
+    data: &'a [u8],
+    kind: io::ErrorKind,
+    failures: u32,
+    failed: u32,
+}
+
+impl Read for FlakyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.failed < self.failures {
+            self.failed += 1;
+            return Err(self.kind.into());
+        }
+        self.failed = 0;
+
+        let n = self.data.len().min(buf.len()).min(100);
+        buf[..n].copy_from_slice(&self.data[..n]);
+        self.data = &self.data[n..];
+        Ok(n)
+    }
+}
+
+#[test]
+fn roundtrip_io_retry() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(100);
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let decode = |kind, failures, attempts| {
+        let reader = FlakyReader {
+            data: &encoded,
+            kind,
+            failures,
+            failed: 0,
+        };
+        let options = DecodeOptions::default().io_retry(attempts, Duration::from_millis(1));
+        let mut decoded: Vec<u8> = Vec::new();
+        Decoder::new_with_options(reader, options)
+            .decode(&mut decoded)
+            .map(|_| decoded)
+    };
+
+    let decoded = decode(io::ErrorKind::TimedOut, 3, 3).expect("failed to decode");
+    assert_eq!(decoded, input);
+
+    let result = decode(io::ErrorKind::TimedOut, 3, 2);
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::IoError(Arc::new(io::ErrorKind::TimedOut.into()))
+    );
+
+    let result = decode(io::ErrorKind::PermissionDenied, 1, 3);
+    assert_eq!(
+        result.unwrap_err(),
+        LzipError::IoError(Arc::new(io::ErrorKind::PermissionDenied.into()))
+    );
+}
+
+#[test]
+fn roundtrip_output_crc() {
+    let input = b"the quick brown fox jumps over the lazy dog\n".repeat(2000);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let (crc32, stats) = Encoder::new(input.as_slice())
+        .encode_with_output_crc(&mut encoded)
+        .expect("failed to encode");
+    assert_eq!(crc32, crc32fast::hash(&encoded));
+    assert_eq!(stats.members, 1);
+    assert_eq!(stats.uncompressed_size, input.len() as u64);
+    assert_eq!(stats.compressed_size, encoded.len() as u64);
+
+    let options = EncoderOptions::default()
+        .block_size(32 * 1024)
+        .append_index(true);
+    let mut encoded: Vec<u8> = Vec::new();
+    let (crc32, stats) = Encoder::new_with_options(input.as_slice(), options)
+        .encode_with_output_crc(&mut encoded)
+        .expect("failed to encode");
+    assert_eq!(crc32, crc32fast::hash(&encoded));
+    assert_eq!(stats.members, 3);
+    assert_eq!(stats.uncompressed_size, input.len() as u64);
+    assert_eq!(stats.compressed_size, encoded.len() as u64);
+}
+
+#[test]
+fn roundtrip_step() {
+    let members: [&[u8]; 2] = [b"the quick brown fox ", b"jumps over the lazy dog"];
+    let mut encoded: Vec<u8> = Vec::new();
+    for member in members {
+        Encoder::new(member)
+            .encode(&mut encoded)
+            .expect("failed to encode");
+    }
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(encoded.as_slice(), options);
+    assert_eq!(decoder.state(), DecoderState::ReadingHeader);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut states = vec![decoder.state()];
+    while decoder.state() != DecoderState::Done {
+        let state = decoder.step(&mut decoded).expect("failed to step");
+        assert_eq!(state, decoder.state());
+        if states.last() != Some(&state) {
+            states.push(state);
+        }
+    }
+
+    assert_eq!(decoded, members.concat());
+    assert_eq!(
+        states,
+        [
+            DecoderState::ReadingHeader,
+            DecoderState::Decompressing,
+            DecoderState::ReadingTrailer,
+            DecoderState::ReadingHeader,
+            DecoderState::Decompressing,
+            DecoderState::ReadingTrailer,
+            DecoderState::Done,
+        ]
+    );
+
+    // Further steps do nothing.
+    assert_eq!(decoder.step(&mut decoded), Ok(DecoderState::Done));
+    assert_eq!(decoded, members.concat());
+}
+
+#[test]
+fn roundtrip_maybe_decoder() {
+    let input = b"the quick brown fox jumps over the lazy dog";
+    let encoded = Encoder::new(input.as_slice())
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    let mut reader = MaybeDecoder::new(encoded.as_slice()).expect("failed to sniff");
+    assert!(reader.is_lzip());
+    let mut decoded: Vec<u8> = Vec::new();
+    reader.read_to_end(&mut decoded).expect("failed to read");
+    assert_eq!(decoded, input);
+
+    // Plain data, including data shorter than the magic or starting like it, is passed through
+    // with its prefix.
+    for plain in [&input[..], b"", b"LZ", b"LZIQ and more", b"LZI"] {
+        let mut reader = MaybeDecoder::new(plain).expect("failed to sniff");
+        assert!(!reader.is_lzip());
+        let mut passed: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut passed).expect("failed to read");
+        assert_eq!(passed, plain);
+    }
+
+    // Corrupt lzip data fails like it does with a decoder.
+    let mut corrupt = encoded.clone();
+    corrupt[4] = 0x07;
+    let mut reader = MaybeDecoder::new(corrupt.as_slice()).expect("failed to sniff");
+    assert!(reader.is_lzip());
+    assert!(reader.read_to_end(&mut Vec::new()).is_err());
+}
@@ -0,0 +1,397 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::decoder::CONCATENATED;
+use lzipper::{
+    DecodeOptions, Decoder, Encoder, EncoderOptions, IndexEntry, LzipError, quick_check,
+    split_members,
+};
+
+use std::io::Cursor;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn encode_members(members: &[&[u8]]) -> Vec<u8> {
+    let mut encoded: Vec<u8> = Vec::new();
+    for member in members {
+        let mut encoder = Encoder::new(*member);
+        encoder.encode(&mut encoded).expect("failed to encode");
+    }
+
+    encoded
+}
+
+#[test]
+fn total_uncompressed_size() {
+    let encoded = encode_members(&[b"the quick brown fox ", b"", b"jumps over the lazy dog"]);
+
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    let size = decoder
+        .total_uncompressed_size()
+        .expect("failed to read size");
+    assert_eq!(size, 43);
+
+    let mut decoded: Vec<u8> = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded.as_slice(), b"the quick brown fox ");
+}
+
+#[test]
+fn first_member_size() {
+    let members: [&[u8]; 3] = [b"the quick brown fox ", b"", b"jumps over the lazy dog"];
+    let mut encoded = Cursor::new(encode_members(&members));
+
+    let mut decoder = Decoder::new(&mut encoded);
+    assert_eq!(decoder.first_member_size(), Ok(20));
+    drop(decoder);
+    assert_eq!(encoded.position(), 0);
+
+    let mut decoder = Decoder::new(&mut encoded);
+    assert_eq!(decoder.first_member_size(), Ok(20));
+    let mut decoded: Vec<u8> = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded.as_slice(), members[0]);
+
+    let mut decoder = Decoder::new(Cursor::new(Vec::new()));
+    assert_eq!(
+        decoder.first_member_size(),
+        Err(LzipError::UnexpectedEndOfStream)
+    );
+}
+
+#[test]
+fn total_uncompressed_size_misaligned() {
+    let mut encoded = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+    encoded.insert(0, 0);
+
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    let result = decoder.total_uncompressed_size();
+
+    assert_eq!(result.unwrap_err(), LzipError::InvalidMemberSize);
+}
+
+#[test]
+fn rewrite_dict_size() {
+    let mut encoded = Cursor::new(encode_members(&[
+        b"the quick brown fox ",
+        b"jumps over the lazy dog",
+    ]));
+    assert_eq!(encoded.get_ref()[5], 0x17); // 8 MiB
+
+    lzipper::rewrite_dict_size(&mut encoded, 1 << 12).expect("failed to rewrite");
+    assert_eq!(encoded.position(), 0);
+    assert_eq!(encoded.get_ref()[5], 0x0C); // 4 KiB
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(encoded, options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(
+        decoded.as_slice(),
+        b"the quick brown fox jumps over the lazy dog"
+    );
+}
+
+#[test]
+fn rewrite_dict_size_too_small() {
+    let input = vec![0; 1024 * 1024]; // 1 MiB of zeros
+    let mut encoded = Cursor::new(encode_members(&[&input]));
+
+    let result = lzipper::rewrite_dict_size(&mut encoded, 1 << 12);
+    assert_eq!(result.unwrap_err(), LzipError::InvalidDictSize);
+    assert_eq!(encoded.get_ref()[5], 0x17); // unchanged
+}
+
+#[test]
+fn quick_check_members() {
+    let single = encode_members(&[b"the quick brown fox jumps over the lazy dog"]);
+    assert_eq!(quick_check(&single), Ok(()));
+
+    let multi = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+    assert_eq!(quick_check(&multi), Ok(()));
+
+    assert_eq!(
+        quick_check(&single[..single.len() - 1]),
+        Err(LzipError::InvalidMemberSize)
+    );
+    assert_eq!(
+        quick_check(&single[..10]),
+        Err(LzipError::UnexpectedEndOfStream)
+    );
+
+    let mut bad_magic = single.clone();
+    bad_magic[0] = b'X';
+    assert_eq!(quick_check(&bad_magic), Err(LzipError::InvalidMagic));
+
+    let mut bad_dict = single.clone();
+    bad_dict[5] = 0;
+    assert_eq!(quick_check(&bad_dict), Err(LzipError::InvalidDictSize));
+}
+
+#[test]
+fn split_two_members() {
+    let encoded = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+
+    let members = split_members(&encoded).expect("failed to split");
+    assert_eq!(members.len(), 2);
+    assert_eq!(members.concat(), encoded);
+
+    let mut decoded: Vec<Vec<u8>> = Vec::new();
+    for member in &members {
+        let mut member_decoded: Vec<u8> = Vec::new();
+        let mut decoder = Decoder::new(member.as_slice());
+        decoder
+            .decode(&mut member_decoded)
+            .expect("failed to decode");
+        decoded.push(member_decoded);
+    }
+    assert_eq!(decoded[0], b"the quick brown fox ");
+    assert_eq!(decoded[1], b"jumps over the lazy dog");
+
+    let mut trailing = encoded.clone();
+    trailing.extend_from_slice(b"garbage");
+    assert_eq!(split_members(&trailing), Err(LzipError::InvalidMemberSize));
+}
+
+#[test]
+fn verify_members_in_parallel() {
+    let encoded = encode_members(&[
+        b"the quick ",
+        b"brown fox ",
+        b"jumps over ",
+        b"the lazy dog",
+    ]);
+
+    for threads in [1, 2, 3] {
+        let mut decoder = Decoder::new(Cursor::new(&encoded));
+        decoder.verify_parallel(threads).expect("failed to verify");
+
+        let mut decoded: Vec<u8> = Vec::new();
+        decoder.decode(&mut decoded).expect("failed to decode");
+        assert_eq!(decoded.as_slice(), b"the quick ");
+    }
+
+    // Corrupt the CRC32 in the trailer of the third member.
+    let members = split_members(&encoded).expect("failed to split");
+    let crc_offset = members[..3].iter().map(Vec::len).sum::<usize>() - 20;
+    let mut corrupt = encoded.clone();
+    corrupt[crc_offset] ^= 0xff;
+
+    let mut decoder = Decoder::new(Cursor::new(corrupt));
+    let result = decoder.verify_parallel(2);
+    assert_eq!(
+        result,
+        Err(LzipError::Corrupt {
+            member: 2,
+            offset: crc_offset as u64,
+            error: Box::new(LzipError::InvalidCrc),
+        })
+    );
+}
+
+#[test]
+fn decode_to_file() {
+    let encoded = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let path = std::env::temp_dir().join(format!("test_decode_to_file_{}.txt", timestamp));
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(&encoded), options);
+    decoder.decode_to_file(&path).expect("failed to decode");
+    assert_eq!(
+        std::fs::read(&path).expect("failed to read output file"),
+        b"the quick brown fox jumps over the lazy dog"
+    );
+
+    // Only the first member is decoded, so the preallocated file is truncated again.
+    let mut decoder = Decoder::new(Cursor::new(&encoded));
+    decoder.decode_to_file(&path).expect("failed to decode");
+    let metadata = std::fs::metadata(&path).expect("failed to stat output file");
+    assert_eq!(metadata.len(), 20);
+    assert_eq!(
+        std::fs::read(&path).expect("failed to read output file"),
+        b"the quick brown fox "
+    );
+
+    std::fs::remove_file(&path).expect("failed to remove output file");
+}
+
+#[test]
+fn decode_to_file_corrupt() {
+    let text = b"the quick brown fox jumps over the lazy dog\n".repeat(5000);
+    let input: Vec<u8> = text
+        .iter()
+        .enumerate()
+        .map(|(i, byte)| byte ^ (i.wrapping_mul(i) >> 7) as u8)
+        .collect();
+    let encoded = encode_members(&[b"the quick brown fox ", &input]);
+
+    // Damage the start of the second member's LZMA data, so decoding fails early in it.
+    let second = split_members(&encoded).expect("failed to split")[0].len();
+    let mut corrupt = encoded.clone();
+    corrupt[second + 16] ^= 0xff;
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis();
+    let path = std::env::temp_dir().join(format!("test_decode_to_file_corrupt_{}.txt", timestamp));
+
+    // The file is preallocated to the full size, but cut back to what was decoded.
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder = Decoder::new_with_options(Cursor::new(&corrupt), options);
+    assert!(decoder.decode_to_file(&path).is_err());
+
+    let decoded = std::fs::read(&path).expect("failed to read output file");
+    std::fs::remove_file(&path).expect("failed to remove output file");
+    assert!(decoded.len() < 20 + input.len());
+    assert_eq!(decoded[..20], *b"the quick brown fox ");
+}
+
+#[test]
+fn is_complete() {
+    let encoded = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+
+    let mut decoder = Decoder::new(Cursor::new(encoded.clone()));
+    assert_eq!(decoder.is_complete(), Ok(true));
+
+    let mut decoded: Vec<u8> = Vec::new();
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded.as_slice(), b"the quick brown fox ");
+
+    // Cut inside the compressed data of the last member.
+    let payload = encoded.len() - 25;
+    let mut decoder = Decoder::new(Cursor::new(&encoded[..payload]));
+    assert_eq!(decoder.is_complete(), Ok(false));
+
+    // Cut inside the trailer of the last member.
+    let trailer = encoded.len() - 8;
+    let mut decoder = Decoder::new(Cursor::new(&encoded[..trailer]));
+    assert_eq!(decoder.is_complete(), Ok(false));
+
+    // Cut inside the first header.
+    let mut decoder = Decoder::new(Cursor::new(&encoded[..3]));
+    assert_eq!(decoder.is_complete(), Ok(false));
+
+    let mut decoder = Decoder::new(Cursor::new(b"not an lzip file".as_slice()));
+    assert_eq!(decoder.is_complete(), Err(LzipError::InvalidMagic));
+}
+
+#[test]
+fn uncompressed_size_in_memory() {
+    let encoded = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+    assert_eq!(lzipper::uncompressed_size(&encoded), Ok(43));
+
+    assert_eq!(
+        lzipper::uncompressed_size(&encoded[..encoded.len() - 1]),
+        Err(LzipError::InvalidMemberSize)
+    );
+
+    let mut trailing = encoded.clone();
+    trailing.extend_from_slice(b"garbage");
+    assert_eq!(
+        lzipper::uncompressed_size(&trailing),
+        Err(LzipError::InvalidMemberSize)
+    );
+
+    assert_eq!(
+        lzipper::uncompressed_size(&[]),
+        Err(LzipError::UnexpectedEndOfStream)
+    );
+}
+
+#[test]
+fn decode_with_offset() {
+    let mut embedded = b"container header".to_vec();
+    assert_eq!(embedded.len(), 16);
+    embedded.extend(encode_members(&[
+        b"the quick brown fox jumps over the lazy dog",
+    ]));
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder = Decoder::with_offset(embedded.as_slice(), 16).expect("failed to skip");
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, b"the quick brown fox jumps over the lazy dog");
+    assert_eq!(
+        decoder.compressed_bytes_consumed(),
+        embedded.len() as u64 - 16
+    );
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let mut decoder =
+        Decoder::with_offset_seekable(Cursor::new(&embedded), 16).expect("failed to seek");
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(decoded, b"the quick brown fox jumps over the lazy dog");
+
+    let result = Decoder::with_offset(&embedded[..10], 16);
+    assert_eq!(result.err(), Some(LzipError::UnexpectedEndOfStream));
+}
+
+#[test]
+fn read_appended_index() {
+    let input: Vec<u8> = (0..10_000u32)
+        .flat_map(|i| (i % 251).to_le_bytes())
+        .collect();
+    let options = EncoderOptions::default()
+        .block_size(16 * 1024)
+        .append_index(true);
+
+    let mut encoded: Vec<u8> = Vec::new();
+    Encoder::new_with_options(input.as_slice(), options)
+        .encode(&mut encoded)
+        .expect("failed to encode");
+
+    let index = Decoder::new(Cursor::new(&encoded))
+        .read_index()
+        .expect("failed to read index");
+    assert_eq!(index.len(), 3);
+    assert_eq!(index[0].offset, 0);
+    assert_eq!(
+        index
+            .iter()
+            .map(|entry| entry.uncompressed_size)
+            .sum::<u64>(),
+        input.len() as u64
+    );
+
+    // Any member decodes on its own from the location in the index.
+    let entry = index[2];
+    let member = &encoded[entry.offset as usize..(entry.offset + entry.member_size) as usize];
+    let mut decoded: Vec<u8> = Vec::new();
+    Decoder::new(member)
+        .decode(&mut decoded)
+        .expect("failed to decode member");
+    assert_eq!(decoded, input[32 * 1024..]);
+
+    // The whole file still decodes, skipping the index.
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    Decoder::new_with_options(encoded.as_slice(), options)
+        .decode(&mut decoded)
+        .expect("failed to decode");
+    assert_eq!(decoded, input);
+
+    // Without an index, the trailers are walked instead.
+    let unindexed = encode_members(&[b"the quick brown fox ", b"jumps over the lazy dog"]);
+    let index = Decoder::new(Cursor::new(&unindexed))
+        .read_index()
+        .expect("failed to walk members");
+    assert_eq!(
+        index,
+        [
+            IndexEntry {
+                offset: 0,
+                member_size: unindexed.len() as u64 - index[1].member_size,
+                uncompressed_size: 20,
+            },
+            IndexEntry {
+                offset: index[1].offset,
+                member_size: index[1].member_size,
+                uncompressed_size: 23,
+            },
+        ]
+    );
+}
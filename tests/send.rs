@@ -0,0 +1,41 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+use lzipper::{DecodeOptions, Decoder, Encoder, EncoderOptions};
+
+use std::io::{Cursor, Read};
+use std::thread;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn send_sync_bounds() {
+    assert_send_sync::<Encoder<Cursor<Vec<u8>>>>();
+    assert_send_sync::<Encoder<&[u8]>>();
+    assert_send_sync::<Decoder<Cursor<Vec<u8>>>>();
+    assert_send_sync::<Decoder<&[u8]>>();
+    assert_send_sync::<EncoderOptions>();
+    assert_send_sync::<DecodeOptions>();
+}
+
+#[test]
+fn decoder_moved_mid_member() {
+    let input = vec![7; 1024 * 1024];
+    let encoded = Encoder::new(Cursor::new(input.clone()))
+        .finish_to_vec()
+        .expect("failed to encode");
+
+    // Start decoding on this thread, so the LZMA stream is live when the decoder moves.
+    let mut decoder = Decoder::new(Cursor::new(encoded));
+    let mut decoded = vec![0; 4096];
+    decoder.read_exact(&mut decoded).expect("failed to decode");
+
+    let decoded = thread::spawn(move || {
+        decoder.read_to_end(&mut decoded).expect("failed to decode");
+        decoded
+    })
+    .join()
+    .expect("decoder thread panicked");
+
+    assert_eq!(decoded, input);
+}
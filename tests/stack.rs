@@ -0,0 +1,84 @@
+// SPDX-License-Identifier: GPL-2.0-or-later
+// SPDX-FileCopyrightText: 2025 Azhar Momin <azharmomin@proton.me>
+
+//! Heap usage of the stack encoder and decoder, measured with a counting global allocator.
+//!
+//! This binary holds a single test so no other test allocates concurrently. Only allocations
+//! made from Rust are counted; liblzma allocates its state through the C allocator.
+
+use lzipper::decoder::CONCATENATED;
+use lzipper::{DecodeOptions, Encoder, LzipError, StackDecoder, StackEncoder};
+
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    io,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A global allocator tracking the current and peak number of allocated bytes.
+struct CountingAllocator;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+#[test]
+fn test_stack_buffers() {
+    let input: Vec<u8> = b"the quick brown fox jumps over the lazy dog\n"
+        .iter()
+        .copied()
+        .cycle()
+        .take(64 * 1024)
+        .collect();
+
+    let mut encoded: Vec<u8> = Vec::new();
+    let mut encoder: StackEncoder<_, 1024> = StackEncoder::new(input.as_slice());
+    encoder.encode(&mut encoded).expect("failed to encode");
+    Encoder::new(b"!".as_slice())
+        .encode(&mut encoded)
+        .expect("failed to encode");
+
+    // Decode into a sink, so only the decoder's own allocations are counted.
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder: StackDecoder<_, 1024> =
+        StackDecoder::new_with_options(encoded.as_slice(), options);
+    let stats = decoder.decode(&mut io::sink()).expect("failed to decode");
+    assert_eq!(stats.members, 2);
+
+    // Setting up the LZMA filters allocates a little, but no working buffers are.
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+    assert!(peak < 1024, "peak allocation of {peak} bytes");
+
+    let mut decoded: Vec<u8> = Vec::new();
+    let options = DecodeOptions::default().lzma_flags(CONCATENATED);
+    let mut decoder: StackDecoder<_, 1024> =
+        StackDecoder::new_with_options(encoded.as_slice(), options);
+    decoder.decode(&mut decoded).expect("failed to decode");
+    assert_eq!(&decoded[..input.len()], input.as_slice());
+    assert_eq!(&decoded[input.len()..], b"!");
+
+    let mut decoder: StackDecoder<_, 1024> = StackDecoder::new(&encoded[..20]);
+    let result = decoder.decode(&mut io::sink());
+    assert_eq!(result.unwrap_err(), LzipError::UnexpectedEndOfStream);
+}